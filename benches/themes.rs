@@ -0,0 +1,57 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use tempfile::TempDir;
+use theme_picker::models::config::Config;
+use theme_picker::models::settings::Settings;
+use theme_picker::models::theme::Theme;
+use theme_picker::services::theme_service::ThemeService;
+use theme_picker::utils::paths::Paths;
+
+/// How many themes the theme directory contains.
+const THEME_COUNT: usize = 60;
+
+/// Writes [`THEME_COUNT`] themes, each with a metadata file and a variables file, into the
+/// directory.
+fn write_themes(directory: &Path) {
+    for index in 0..THEME_COUNT {
+        let theme_path = directory.join(format!("theme-{index}"));
+        fs::create_dir(&theme_path).unwrap();
+        fs::write(
+            theme_path.join("meta.toml"),
+            format!(
+                "name = \"Theme {index}\"\ndescription = \"Theme number {index}\"\ncolor_scheme = \"Dark\"\ngtk_theme = \"Adwaita-dark\"\ntags = [\"dark\", \"warm\"]\n"
+            ),
+        )
+        .unwrap();
+        fs::write(
+            theme_path.join(Theme::DEFAULT_VARIABLES_FILE_NAME),
+            "$backgroundColor: #1f1f28;\n",
+        )
+        .unwrap();
+    }
+}
+
+fn many_themes(c: &mut Criterion) {
+    let directory = TempDir::new().unwrap();
+    write_themes(directory.path());
+
+    let settings = Settings {
+        paths: Paths::new(
+            directory.path().to_path_buf(),
+            directory.path().to_path_buf(),
+        ),
+        config: Config::default(),
+    };
+
+    c.bench_function("get_available_themes with 60 themes", |b| {
+        b.iter(|| {
+            let themes = ThemeService::get_available_themes(black_box(&settings)).unwrap();
+            assert_eq!(themes.len(), THEME_COUNT);
+        });
+    });
+}
+
+criterion_group!(benches, many_themes);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use tempfile::TempDir;
+use theme_picker::models::theme::{ColorScheme, Theme};
+use theme_picker::services::theme_service::ThemeService;
+
+/// How many partials deep the theme's variables are `@use`d.
+const DEPTH: usize = 100;
+
+/// Writes a theme whose variables file uses a chain of [`DEPTH`] partials, each of which also uses
+/// a shared palette, so that the palette is reached through every level of the chain.
+fn write_deep_import_theme(directory: &Path) -> Theme {
+    fs::write(
+        directory.join("palette.scss"),
+        "$bg: rgba(31, 31, 40, 1);\n$fg: rgba(220, 215, 186, 1);\n",
+    )
+    .unwrap();
+
+    for level in 0..DEPTH {
+        let used = if level == 0 {
+            String::new()
+        } else {
+            format!("@use \"level-{}\" as *;\n", level - 1)
+        };
+
+        fs::write(
+            directory.join(format!("level-{level}.scss")),
+            format!(
+                "@use \"palette\";\n{used}$color-{level}: palette.$bg;\n$border-{level}: 1px solid palette.$fg;\n"
+            ),
+        )
+        .unwrap();
+    }
+
+    fs::write(
+        directory.join(Theme::DEFAULT_VARIABLES_FILE_NAME),
+        format!(
+            "@use \"level-{}\" as *;\n$backgroundColor: $color-0;\n",
+            DEPTH - 1
+        ),
+    )
+    .unwrap();
+
+    Theme::new(
+        "Deep",
+        "",
+        directory.to_path_buf(),
+        None,
+        ColorScheme::Dark,
+        "",
+        Theme::DEFAULT_VARIABLES_FILE_NAME,
+    )
+}
+
+fn deep_imports(c: &mut Criterion) {
+    let directory = TempDir::new().unwrap();
+    let theme = write_deep_import_theme(directory.path());
+
+    c.bench_function("get_variables with deep imports", |b| {
+        b.iter(|| ThemeService::get_variables(black_box(&theme)).unwrap());
+    });
+}
+
+criterion_group!(benches, deep_imports);
+criterion_main!(benches);
@@ -1,58 +1,1607 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::CompleteEnv;
+use clap_complete::engine::{ArgValueCompleter, CompletionCandidate};
+use serde::Serialize;
+use serde_json::json;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use theme_picker::models::apply_error::ApplyError;
+use theme_picker::models::apply_options::ApplyOptions;
+use theme_picker::models::color_blindness::ColorBlindness;
+use theme_picker::models::component::Component;
+use theme_picker::models::config::WaybarReload;
+use theme_picker::models::hex_color::HexColor;
+use theme_picker::models::loaded_themes::LoadedThemes;
+use theme_picker::models::rgba_color::RGBAColor;
+use theme_picker::models::settings::Settings;
+use theme_picker::models::theme::Theme;
+use theme_picker::models::theme_issue::ThemeIssue;
+use theme_picker::models::wallpaper_error::WallpaperError;
+use theme_picker::services::config_service::ConfigService;
+use theme_picker::services::doctor_service::DoctorService;
+use theme_picker::services::integration_service::IntegrationService;
 use theme_picker::services::theme_service::ThemeService;
+use theme_picker::services::verification_service::{Snapshot, VerificationService};
+use theme_picker::utils::command_runner::CommandRunner;
+use theme_picker::utils::directory::Directory;
+use theme_picker::utils::paths::Paths;
 
 #[derive(Parser)]
 #[command(name = "norlyk", about = "Norlyk settings manager", version)]
 struct Args {
+    /// Read themes from this directory instead of ~/.local/share/norlyk-themes
+    #[arg(long, global = true)]
+    theme_dir: Option<PathBuf>,
+
+    /// Load the global configuration from this file instead of ~/.config/norlyk/config.toml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Print results and errors as JSON objects on stdout, for use from other programs
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Write the generated configuration files under this directory instead of the home directory,
+    /// without reloading any applications, e.g. to review them before committing them to dotfiles
+    #[arg(long, global = true)]
+    output_dir: Option<PathBuf>,
+
+    /// Write the generated configuration files without reloading any applications, e.g. on a
+    /// machine without Hyprland. Also turned on by `NORLYK_DEV=1`
+    #[arg(long, global = true)]
+    no_reload: bool,
+
+    /// Only accept the exact name or directory name of a theme, instead of also an unambiguous
+    /// part of it, e.g. in scripts
+    #[arg(long, global = true)]
+    exact: bool,
+
+    /// Whether to color the output. `auto` colors it when writing to a terminal, unless
+    /// `NO_COLOR` is set
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    Theme {
+    Theme(ThemeArgs),
+    /// Apply the default theme, or the last applied theme if there is no default, e.g. at login
+    ApplyDefault,
+    /// Apply the current theme again, e.g. after editing its variables
+    Reload,
+    /// List the available themes
+    List {
+        #[arg(long, value_enum, default_value_t = ListFormat::Plain)]
+        format: ListFormat,
+        /// Include the number of wallpapers of each theme, warning about themes without any
+        #[arg(long)]
+        verbose: bool,
+        /// Only list themes with files modified within this duration, e.g. 2h or 1d
+        #[arg(long, value_parser = parse_interval)]
+        modified_since: Option<Duration>,
+        /// Only list themes with this tag in their metadata. Can be given several times, in which
+        /// case a theme must have all of the tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// List the themes with any of the tags, instead of all of them
+        #[arg(long, requires = "tags")]
+        any: bool,
+    },
+    /// Print the absolute path of the theme's directory, or with `current`, of the current theme's
+    ThemePath {
+        #[arg(add = ArgValueCompleter::new(complete_theme_name))]
+        name: String,
+    },
+    /// Open the theme's variables file in $EDITOR
+    Edit {
+        #[arg(add = ArgValueCompleter::new(complete_theme_name))]
         name: String,
     },
     Wallpaper {
         #[command(subcommand)]
         action: WallpaperAction,
     },
+    /// Check the environment for everything needed to apply themes
+    Doctor,
+    /// List the components a theme would be applied to on this system, and why the others would be
+    /// skipped
+    Components,
+    /// Print the theme's variables, with a swatch of each color in truecolor terminals
+    Palette {
+        /// The name of the theme
+        #[arg(add = ArgValueCompleter::new(complete_theme_name))]
+        name: String,
+        /// Show the swatches as they look with a kind of color blindness
+        #[arg(long, value_enum, default_value_t = ColorBlindness::Normal)]
+        simulate: ColorBlindness,
+    },
+    /// Fill in the `__variableName__` placeholders of a template with the theme's variables, like
+    /// Kitty's theme-template.conf, without reloading any application
+    Render {
+        /// The name of the theme
+        #[arg(add = ArgValueCompleter::new(complete_theme_name))]
+        name: String,
+        /// The template to fill in
+        #[arg(long)]
+        template: PathBuf,
+        /// The file to write the result to
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Print the theme's variables in a format for other tools, e.g. as CSS custom properties
+    ExportVars {
+        /// The name of the theme
+        #[arg(add = ArgValueCompleter::new(complete_theme_name))]
+        name: String,
+        #[arg(long = "as", value_enum, default_value_t = ExportFormat::Css)]
+        format: ExportFormat,
+    },
 }
 
-#[derive(Subcommand)]
+// The arguments of `norlyk theme`, without a doc comment that would become its about text
+#[derive(clap::Args)]
+#[allow(clippy::struct_excessive_bools)] // Independent command line flags
+struct ThemeArgs {
+    #[arg(required_unless_present = "stdin", add = ArgValueCompleter::new(complete_theme_name))]
+    name: Option<String>,
+    /// Read the theme name from the first line of stdin, e.g. from a dmenu-style picker
+    #[arg(long, conflicts_with = "name")]
+    stdin: bool,
+    /// Check that the applications picked up the theme, exiting non-zero if any did not
+    #[arg(long, conflicts_with_all = ["output_dir", "no_reload"])]
+    verify: bool,
+    /// Copy the theme into the current theme directory instead of symlinking it
+    #[arg(long)]
+    copy_current: bool,
+    /// Write the variables sorted by name, so that the generated files are stable in version control
+    #[arg(long)]
+    sorted: bool,
+    /// Print the path and contents of every generated file to stderr
+    #[arg(long, short)]
+    verbose: bool,
+    /// Apply the theme even if it is already the current theme
+    #[arg(long, short)]
+    force: bool,
+    /// Only write Waybar's stylesheet, for setups where something else watches it and reloads
+    /// Waybar
+    #[arg(long)]
+    no_waybar_restart: bool,
+    /// Apply the theme without asking, even if problems with it were found
+    #[arg(long, short)]
+    yes: bool,
+    /// Record the theme as the default theme applied by `apply-default`, instead of applying it
+    #[arg(long)]
+    set_default: bool,
+    /// Only apply the theme to this component, leaving the others untouched. Can be given
+    /// several times
+    #[arg(long, value_enum)]
+    apply_to: Vec<Component>,
+    /// Write the generated configuration files to a new temporary directory and print their
+    /// paths, without reloading any applications
+    #[arg(long, conflicts_with_all = ["output_dir", "verify", "set_default"])]
+    preview_only: bool,
+    /// Open the previewed files in $PAGER, or `less` if it is not set
+    #[arg(long, requires = "preview_only")]
+    pager: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// A `:root` rule with a custom property per variable, e.g. `--backgroundColor: ...;`
+    Css,
+    /// A Sass variable per line, e.g. `$backgroundColor: ...;`
+    Scss,
+    /// An object with the value of each variable
+    Json,
+    /// Environment variable assignments, e.g. `NORLYK_BACKGROUND_COLOR='...'`
+    Env,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether output written to a stream is colored, which in `auto` mode is when the stream is a
+    /// terminal and the `NO_COLOR` environment variable is not set, see <https://no-color.org>.
+    fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                is_terminal && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ListFormat {
+    /// One theme name per line
+    Plain,
+    /// An array of objects with the directory name, name and description of each theme
+    Json,
+    /// Rows of directory name, name and description, with a header row
+    Csv,
+}
+
+#[derive(Serialize)]
+struct ThemeListing {
+    dir_name: String,
+    name: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wallpaper_count: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+}
+
+/// Kinds of errors, reported as `kind` in JSON mode so that other programs do not have to parse
+/// the messages.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ErrorKind {
+    InvalidThemeDir,
+    InvalidStateDir,
+    InvalidConfig,
+    InvalidInput,
+    InvalidThemeName,
+    ThemeNotFound,
+    AmbiguousThemeName,
+    ThemeHasIssues,
+    ThemeIncomplete,
+    ThemesUnavailable,
+    DefaultThemeFailed,
+    NoCurrentTheme,
+    ApplyFailed,
+    PartiallyApplied,
+    VerificationFailed,
+    EditFailed,
+    WallpaperFailed,
+    WallpaperTimedOut,
+    WallpaperProgramMissing,
+    WallpaperNotReady,
+    WallpaperRejected,
+    PaletteFailed,
+    ExportFailed,
+    RenderFailed,
+}
+
+impl From<&WallpaperError> for ErrorKind {
+    fn from(error: &WallpaperError) -> Self {
+        match error {
+            WallpaperError::Failed(_) | WallpaperError::UnexpectedResponse(_) => {
+                ErrorKind::WallpaperFailed
+            }
+            WallpaperError::TimedOut(_) => ErrorKind::WallpaperTimedOut,
+            WallpaperError::ProgramMissing(_) => ErrorKind::WallpaperProgramMissing,
+            WallpaperError::NotReady(_) => ErrorKind::WallpaperNotReady,
+            WallpaperError::Rejected(_) => ErrorKind::WallpaperRejected,
+        }
+    }
+}
+
+/// Reports results as human-readable text, or as a JSON object per result on stdout, e.g.
+/// `{"status":"error","kind":"theme_not_found","message":"..."}`.
+struct Output {
+    json: bool,
+    /// Whether ANSI colors are written to stdout.
+    color: bool,
+    /// Whether ANSI colors are written to stderr.
+    error_color: bool,
+}
+
+impl Output {
+    const GREEN: &'static str = "32";
+    const RED: &'static str = "31";
+    const YELLOW: &'static str = "33";
+    const DIM: &'static str = "2";
+
+    /// Wraps the text in the ANSI escape codes for the style if `enabled`.
+    fn paint(enabled: bool, style: &str, text: &str) -> String {
+        if enabled {
+            format!("\x1b[{style}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Gets a green check mark if `passed`, and a red cross otherwise, for checklists on stdout.
+    fn mark(&self, passed: bool) -> String {
+        if passed {
+            Self::paint(self.color, Self::GREEN, "✓")
+        } else {
+            Self::paint(self.color, Self::RED, "✗")
+        }
+    }
+
+    /// Prints a warning on stderr, also in JSON mode, where stdout only has the result.
+    fn warning(&self, message: &str) {
+        eprintln!("{}", Self::paint(self.error_color, Self::YELLOW, message));
+    }
+
+    fn success(&self, message: &str) {
+        if self.json {
+            println!("{}", json!({ "status": "ok", "message": message }));
+        } else {
+            println!("{message}");
+        }
+    }
+
+    fn error(&self, kind: ErrorKind, message: &str) {
+        if self.json {
+            println!(
+                "{}",
+                json!({ "status": "error", "kind": kind, "message": message })
+            );
+        } else {
+            eprintln!("{}", Self::paint(self.error_color, Self::RED, message));
+        }
+    }
+}
+
+#[derive(Clone, Copy, Subcommand)]
 enum WallpaperAction {
     Reload,
+    /// Print the active wallpaper of each monitor
+    Current,
+    /// Change the wallpaper to another one from the current theme at a fixed interval
+    Rotate {
+        /// How often to change the wallpaper, e.g. 90s, 15m or 1h30m
+        #[arg(long, default_value = "15m", value_parser = parse_interval)]
+        interval: Duration,
+    },
 }
 
 fn main() {
-    let args = Args::parse();
+    // Answers shell completion requests, e.g. from `source <(COMPLETE=bash norlyk)`
+    CompleteEnv::with_factory(Args::command).complete();
+
+    let mut args = Args::parse();
+    let output = Output {
+        json: args.json,
+        color: args.color.enabled(io::stdout().is_terminal()),
+        error_color: args.color.enabled(io::stderr().is_terminal()),
+    };
+
+    let settings = configure(&output, args.theme_dir.take(), args.config.as_deref());
 
     match args.command {
-        Commands::Theme { name } => {
-            let themes = ThemeService::get_available_themes().unwrap_or_else(|e| {
-                eprintln!("Could not get themes: {e}");
-                Vec::new()
-            });
-
-            let Some(theme) = themes.iter().find(|theme| theme.name.eq(&name)) else {
-                eprintln!("Could not get theme: {name}");
-                eprintln!("Available themes:");
-                for theme in themes {
-                    eprintln!(" - {}", theme.name);
-                }
-                return;
+        Commands::Theme(command) => {
+            theme_command(
+                &output,
+                &settings,
+                command,
+                args.output_dir,
+                args.no_reload,
+                args.exact,
+            );
+        }
+        Commands::ApplyDefault => {
+            apply_default_command(&output, &settings, args.output_dir, args.no_reload);
+        }
+        Commands::Reload => reload_command(&output, &settings, args.output_dir, args.no_reload),
+        Commands::List {
+            format,
+            verbose,
+            modified_since,
+            tags,
+            any,
+        } => list_command(
+            &output,
+            &settings,
+            format,
+            verbose,
+            modified_since,
+            &tags,
+            any,
+        ),
+        Commands::ThemePath { name } => theme_path_command(&output, &settings, &name, args.exact),
+        Commands::Edit { name } => {
+            let Some(theme) = find_theme(&output, &settings, &name, args.exact) else {
+                process::exit(1);
             };
 
-            match ThemeService::set_current_theme(theme) {
-                Ok(()) => println!("The theme was set successfully"),
-                Err(e) => eprintln!("Error setting theme: {e}"),
+            if let Err(e) = ThemeService::edit_variables(&theme) {
+                output.error(ErrorKind::EditFailed, &format!("Error editing theme: {e}"));
+                process::exit(1);
+            }
+        }
+        Commands::Wallpaper { action } => wallpaper_command(&output, &settings, action),
+        Commands::Palette { name, simulate } => {
+            let Some(theme) = find_theme(&output, &settings, &name, args.exact) else {
+                process::exit(1);
+            };
+
+            if let Err(e) = print_palette(&output, &theme, simulate) {
+                output.error(
+                    ErrorKind::PaletteFailed,
+                    &format!("Could not print the palette: {e}"),
+                );
+                process::exit(1);
+            }
+        }
+        Commands::Render {
+            name,
+            template,
+            out,
+        } => render_command(&output, &settings, &name, &template, &out, args.exact),
+        Commands::ExportVars { name, format } => {
+            export_vars_command(&output, &settings, &name, format, args.exact);
+        }
+        Commands::Doctor => {
+            if !doctor(&output, &settings) {
+                process::exit(1);
             }
         }
-        Commands::Wallpaper { action } => match action {
-            WallpaperAction::Reload => match ThemeService::change_wallpaper() {
-                Ok(()) => println!("The wallpaper was reloaded"),
-                Err(e) => eprintln!("Error reloading wallpaper: {e}"),
-            },
+        Commands::Components => {
+            let options = ApplyOptions {
+                output_dir: args.output_dir,
+                no_reload: args.no_reload,
+                ..ApplyOptions::default()
+            };
+
+            print_integrations(&output, &settings, &options);
+        }
+    }
+}
+
+/// Loads the settings with the theme directory and global configuration file given on the command
+/// line, exiting if any of them, or the configured state directory, is invalid.
+fn configure(output: &Output, theme_dir: Option<PathBuf>, config: Option<&Path>) -> Settings {
+    // A missing or invalid file is reported up front instead of by the first command reading it
+    let config = ConfigService::load(config).unwrap_or_else(|e| {
+        output.error(
+            ErrorKind::InvalidConfig,
+            &format!("Could not load the configuration: {e}"),
+        );
+        process::exit(1);
+    });
+
+    let config_path = match theme_dir {
+        Some(theme_dir) => theme_dir,
+        None => Paths::default_config_path().unwrap_or_else(|e| {
+            output.error(
+                ErrorKind::InvalidThemeDir,
+                &format!("Could not find the theme directory: {e}"),
+            );
+            process::exit(1);
+        }),
+    };
+
+    let state_path = ConfigService::state_path(&config, &config_path).unwrap_or_else(|e| {
+        output.error(
+            ErrorKind::InvalidStateDir,
+            &format!("Could not set the state directory: {e}"),
+        );
+        process::exit(1);
+    });
+
+    Settings {
+        paths: Paths::new(config_path, state_path),
+        config,
+    }
+}
+
+/// Prints the absolute path of a theme's directory, for `norlyk theme-path`.
+fn theme_path_command(output: &Output, settings: &Settings, name: &str, exact: bool) {
+    let path = if name == "current" {
+        let Some(path) = ThemeService::current_theme_directory(settings) else {
+            output.error(ErrorKind::NoCurrentTheme, "No theme has been applied yet");
+            process::exit(1);
+        };
+
+        path
+    } else {
+        let Some(theme) = find_theme(output, settings, name, exact) else {
+            process::exit(1);
+        };
+
+        theme.directory_path
+    };
+
+    // Relative theme directories, e.g. from `--theme-dir`, are made absolute for scripts,
+    // and a current theme that was deleted is reported
+    let path = match fs::canonicalize(&path) {
+        Ok(path) => path,
+        Err(e) => {
+            output.error(
+                ErrorKind::ThemeNotFound,
+                &format!("Could not resolve {}: {e}", path.display()),
+            );
+            process::exit(1);
+        }
+    };
+
+    if output.json {
+        println!("{}", json!({ "status": "ok", "path": path }));
+    } else {
+        println!("{}", path.display());
+    }
+}
+
+/// Applies the theme, or records it as the default theme, for `norlyk theme`.
+fn theme_command(
+    output: &Output,
+    settings: &Settings,
+    command: ThemeArgs,
+    output_dir: Option<PathBuf>,
+    no_reload: bool,
+    exact: bool,
+) {
+    let ThemeArgs {
+        name,
+        stdin,
+        verify,
+        copy_current,
+        sorted,
+        verbose,
+        no_waybar_restart,
+        force,
+        yes,
+        set_default,
+        apply_to,
+        preview_only,
+        pager,
+    } = command;
+
+    let name = if stdin {
+        match read_theme_name_from_stdin() {
+            Ok(name) => name,
+            Err(e) => {
+                output.error(ErrorKind::InvalidInput, &e);
+                process::exit(1);
+            }
+        }
+    } else {
+        name.unwrap_or_default()
+    };
+
+    let Some(theme) = find_theme(output, settings, &name, exact) else {
+        process::exit(1);
+    };
+
+    if set_default {
+        match ThemeService::set_default_theme(settings, &theme) {
+            Ok(()) => {
+                output.success(&format!("The default theme was set to {}", theme.name));
+            }
+            Err(e) => {
+                output.error(
+                    ErrorKind::DefaultThemeFailed,
+                    &format!("Error setting default theme: {e}"),
+                );
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Picking components, or previewing, is for trying them out, which the current theme is
+    // as good for
+    let force = force || !apply_to.is_empty() || preview_only;
+
+    let mut options = ApplyOptions {
+        copy_current,
+        sort_variables: sorted,
+        verbose,
+        output_dir,
+        no_reload,
+        waybar_reload: no_waybar_restart.then_some(WaybarReload::None),
+        ..ApplyOptions::default()
+    };
+
+    if !apply_to.is_empty() {
+        options.components = apply_to;
+    }
+
+    if !force && ThemeService::is_current(settings, &theme, &options) {
+        output.success(&format!(
+            "{} is already the current theme, pass --force to apply it again",
+            theme.name
+        ));
+        return;
+    }
+
+    // A preview is written to a scratch directory, so nothing it could fail on is worth a prompt
+    if preview_only {
+        process::exit(preview_theme(output, settings, &theme, options, pager));
+    }
+
+    let issues = ThemeService::find_issues(settings, &theme, &options);
+
+    if let Some(issue) = issues
+        .iter()
+        .find(|issue| matches!(issue, ThemeIssue::MissingVariablesFile(_)))
+    {
+        output.error(
+            ErrorKind::ThemeIncomplete,
+            &format!("{} cannot be applied. {issue}", theme.name),
+        );
+        process::exit(1);
+    }
+
+    if !yes && !issues.is_empty() && !confirm_issues(output, &issues) {
+        process::exit(1);
+    }
+
+    // Applying anyway skips the wallpaper, instead of failing on it
+    if issues.iter().any(|issue| {
+        matches!(
+            issue,
+            ThemeIssue::NoWallpapers(_) | ThemeIssue::InvalidWallpapers(_)
+        )
+    }) {
+        options
+            .components
+            .retain(|component| *component != Component::Wallpaper);
+    }
+
+    process::exit(apply_theme(output, settings, &theme, &options, verify));
+}
+
+/// Applies the current theme again, for `norlyk reload`.
+fn reload_command(
+    output: &Output,
+    settings: &Settings,
+    output_dir: Option<PathBuf>,
+    no_reload: bool,
+) {
+    let themes = match ThemeService::get_available_themes(settings) {
+        Ok(themes) => themes,
+        Err(e) => {
+            output.error(
+                ErrorKind::ThemesUnavailable,
+                &format!("Could not get themes: {e}"),
+            );
+            process::exit(1);
+        }
+    };
+
+    let Some(theme) = ThemeService::get_current_theme(settings, themes) else {
+        output.error(
+            ErrorKind::NoCurrentTheme,
+            "No theme has been applied yet. Apply one with `norlyk theme <name>`",
+        );
+        process::exit(1);
+    };
+
+    // A copied current theme stays a copy, instead of becoming a symlink
+    let options = ApplyOptions {
+        copy_current: {
+            let path = settings.paths.current_theme();
+            path.is_dir() && !path.is_symlink()
         },
+        output_dir,
+        no_reload,
+        ..ApplyOptions::default()
+    };
+
+    process::exit(apply_theme(output, settings, &theme, &options, false));
+}
+
+/// Lists the themes matching the filters, for `norlyk list`.
+fn list_command(
+    output: &Output,
+    settings: &Settings,
+    format: ListFormat,
+    verbose: bool,
+    modified_since: Option<Duration>,
+    tags: &[String],
+    any: bool,
+) {
+    let LoadedThemes {
+        mut themes,
+        skipped,
+    } = match ThemeService::load_themes(settings) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            output.error(
+                ErrorKind::ThemesUnavailable,
+                &format!("Could not get themes: {e}"),
+            );
+            process::exit(1);
+        }
+    };
+
+    for skipped_theme in &skipped {
+        output.warning(&format!("Warning: {skipped_theme}"));
+    }
+
+    if let Some(duration) = modified_since {
+        let since = SystemTime::now()
+            .checked_sub(duration)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        themes.retain(|theme| theme.last_modified().is_some_and(|time| time >= since));
+    }
+
+    themes.retain(|theme| theme.matches_tags(tags, any));
+    let include_videos = settings.config.wallpaper.video;
+
+    if output.json {
+        println!(
+            "{}",
+            json!({ "status": "ok", "themes": theme_listings(&themes, verbose, include_videos) })
+        );
+    } else {
+        list_themes(&themes, format, verbose, include_videos);
+
+        if verbose {
+            warn_about_missing_wallpapers(output, &themes, include_videos);
+        }
+
+        warn_about_incomplete_themes(output, &themes);
+    }
+}
+
+/// Applies the default theme, for `norlyk apply-default`.
+fn apply_default_command(
+    output: &Output,
+    settings: &Settings,
+    output_dir: Option<PathBuf>,
+    no_reload: bool,
+) {
+    match ThemeService::get_default_theme(settings) {
+        Ok(theme) => {
+            let options = ApplyOptions {
+                output_dir,
+                no_reload,
+                ..ApplyOptions::default()
+            };
+
+            process::exit(apply_theme(output, settings, &theme, &options, false));
+        }
+        Err(e) => {
+            output.error(
+                ErrorKind::DefaultThemeFailed,
+                &format!("Could not get the default theme: {e}"),
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Changes or reports the wallpaper, for `norlyk wallpaper`.
+fn wallpaper_command(output: &Output, settings: &Settings, action: WallpaperAction) {
+    match action {
+        WallpaperAction::Reload => match ThemeService::change_wallpaper(settings) {
+            Ok(()) => output.success("The wallpaper was reloaded"),
+            Err(e) => {
+                output.error(
+                    ErrorKind::from(&e),
+                    &format!("Error reloading wallpaper: {e}"),
+                );
+                process::exit(1);
+            }
+        },
+        WallpaperAction::Current => match ThemeService::active_wallpapers() {
+            Ok(wallpapers) => print_active_wallpapers(output, &wallpapers),
+            Err(e) => {
+                output.error(ErrorKind::WallpaperFailed, &e);
+                process::exit(1);
+            }
+        },
+        WallpaperAction::Rotate { interval } => rotate_wallpaper(output, settings, interval),
+    }
+}
+
+/// Fills in a template with the theme's variables, for `norlyk render`.
+fn render_command(
+    output: &Output,
+    settings: &Settings,
+    name: &str,
+    template: &Path,
+    out: &Path,
+    exact: bool,
+) {
+    let Some(theme) = find_theme(output, settings, name, exact) else {
+        process::exit(1);
+    };
+
+    match ThemeService::render_template(&theme, template, out) {
+        Ok(()) => output.success(&format!("Rendered {}", out.display())),
+        Err(e) => {
+            output.error(
+                ErrorKind::RenderFailed,
+                &format!("Could not render the template: {e}"),
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the theme's variables for other tools, for `norlyk export-vars`.
+fn export_vars_command(
+    output: &Output,
+    settings: &Settings,
+    name: &str,
+    format: ExportFormat,
+    exact: bool,
+) {
+    let Some(theme) = find_theme(output, settings, name, exact) else {
+        process::exit(1);
+    };
+
+    match ThemeService::get_variables(&theme)
+        .and_then(|variables| export_variables(&variables, format))
+    {
+        Ok(exported) => print!("{exported}"),
+        Err(e) => {
+            output.error(
+                ErrorKind::ExportFailed,
+                &format!("Could not export the variables: {e}"),
+            );
+            process::exit(1);
+        }
+    }
+}
+
+/// Asks whether to apply a theme with issues when stdin is a terminal, and aborts otherwise, so
+/// that scripts never apply a broken theme unless `--yes` is given.
+fn confirm_issues(output: &Output, issues: &[ThemeIssue]) -> bool {
+    let mut message = "This theme has issues:".to_string();
+    for issue in issues {
+        message.push_str("\n - ");
+        message.push_str(&issue.to_string());
+    }
+
+    if !io::stdin().is_terminal() {
+        output.error(
+            ErrorKind::ThemeHasIssues,
+            &format!("{message}\nPass --yes to apply it anyway"),
+        );
+        return false;
+    }
+
+    eprint!("{message}\nApply anyway? [y/N] ");
+
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Applies the theme, printing how long each step took to stderr in verbose mode, e.g.
+/// `Waybar: 1.8s`.
+fn set_current_theme(
+    settings: &Settings,
+    theme: &Theme,
+    options: &ApplyOptions,
+) -> Result<(), ApplyError> {
+    ThemeService::set_current_theme_with_progress(settings, theme, options, &mut |step| {
+        if options.verbose {
+            eprintln!("{}: {:.1?}", step.step, step.duration);
+        }
+    })
+}
+
+/// Applies the theme, optionally verifying it afterwards, and returns the exit code: 0 on success,
+/// 1 on failure, and 2 if the theme was only partially applied.
+fn apply_theme(
+    output: &Output,
+    settings: &Settings,
+    theme: &Theme,
+    options: &ApplyOptions,
+    verify: bool,
+) -> i32 {
+    let snapshot = VerificationService::snapshot(settings);
+
+    let (exit_code, result) = match set_current_theme(settings, theme, options) {
+        Ok(()) => (0, Ok("The theme was set successfully".to_string())),
+        Err(e @ ApplyError::Partial(_)) => (
+            2,
+            Err((ErrorKind::PartiallyApplied, format!("Warning: {e}"))),
+        ),
+        Err(e @ ApplyError::Failed(_)) => (
+            1,
+            Err((ErrorKind::ApplyFailed, format!("Error setting theme: {e}"))),
+        ),
+    };
+
+    let report = |result: &Result<String, (ErrorKind, String)>| match result {
+        Ok(message) => output.success(message),
+        Err((kind, message)) => output.error(*kind, message),
+    };
+
+    // In JSON mode a single result is printed, so the verification has to come first
+    if !output.json {
+        report(&result);
+    }
+
+    let failed_verifications = if verify {
+        verify_theme(output, settings, theme, &snapshot)
+    } else {
+        Vec::new()
+    };
+
+    if output.json {
+        if result.is_ok() && !failed_verifications.is_empty() {
+            output.error(
+                ErrorKind::VerificationFailed,
+                &format!("Verification failed: {}", failed_verifications.join(", ")),
+            );
+        } else {
+            report(&result);
+        }
+    }
+
+    if failed_verifications.is_empty() {
+        exit_code
+    } else {
+        1
+    }
+}
+
+/// Applies the theme with its configuration files written to a new temporary directory, which is
+/// left in place for reviewing them, and prints the paths of the written files. Returns the exit
+/// code of the application.
+fn preview_theme(
+    output: &Output,
+    settings: &Settings,
+    theme: &Theme,
+    mut options: ApplyOptions,
+    pager: bool,
+) -> i32 {
+    // Created with a random name only the current user can access, and kept for the user to read
+    let preview_dir = match tempfile::Builder::new().prefix("norlyk-preview-").tempdir() {
+        Ok(directory) => directory.keep(),
+        Err(e) => {
+            output.error(
+                ErrorKind::ApplyFailed,
+                &format!("Could not create the preview directory: {e}"),
+            );
+            return 1;
+        }
+    };
+
+    options.output_dir = Some(preview_dir.clone());
+
+    let exit_code = match set_current_theme(settings, theme, &options) {
+        Ok(()) => 0,
+        Err(e @ ApplyError::Partial(_)) => {
+            output.warning(&format!("Warning: {e}"));
+            2
+        }
+        Err(e @ ApplyError::Failed(_)) => {
+            output.error(
+                ErrorKind::ApplyFailed,
+                &format!("Error previewing theme: {e}"),
+            );
+            return 1;
+        }
+    };
+
+    let files = match Directory::files(&preview_dir) {
+        Ok(files) => files,
+        Err(e) => {
+            output.error(ErrorKind::ApplyFailed, &e);
+            return 1;
+        }
+    };
+
+    if output.json {
+        println!(
+            "{}",
+            json!({ "status": "ok", "directory": preview_dir, "files": files })
+        );
+    } else {
+        println!("The preview was written to {}", preview_dir.display());
+        for file in &files {
+            println!("{}", file.display());
+        }
+    }
+
+    if pager && let Err(e) = open_in_pager(&files) {
+        output.error(ErrorKind::InvalidInput, &e);
+        return 1;
+    }
+
+    exit_code
+}
+
+/// Opens the files in the pager given by the `PAGER` environment variable, or `less`, and waits for
+/// it to exit.
+fn open_in_pager(files: &[PathBuf]) -> Result<(), String> {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+
+    let status = CommandRunner::parse(&pager)?
+        .args(files)
+        .status()
+        .map_err(|e| format!("Could not execute '{pager}': {e}"))?;
+
+    if !status.success() {
+        return Err(format!("'{pager}' failed with {status}"));
+    }
+
+    Ok(())
+}
+
+/// Prints each of the theme's variables, with a swatch and the hex value for colors, and the
+/// raw value for anything else. Translucent colors are shown as `#RRGGBBAA`.
+fn print_palette(output: &Output, theme: &Theme, simulation: ColorBlindness) -> Result<(), String> {
+    let variables = ThemeService::get_variables(theme)?;
+    let name_width = variables
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or_default();
+
+    for (name, value) in &variables {
+        let color = HexColor::try_from(value).ok().and_then(|color| {
+            let rgb = color.rgb()?;
+            let hex =
+                RGBAColor::try_from(value).map_or_else(|_| String::from(color), |rgba| rgba.hex());
+
+            Some((rgb, hex))
+        });
+
+        match color {
+            Some((rgb, hex)) if output.color => {
+                let (red, green, blue) = simulation.simulate(rgb);
+                println!("{name:<name_width$}  \x1b[48;2;{red};{green};{blue}m    \x1b[0m  {hex}");
+            }
+            Some((_, hex)) => println!("{name:<name_width$}  {hex}"),
+            None => println!("{name:<name_width$}        {value}"),
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats the variables for other tools, with their values as they are resolved.
+fn export_variables(
+    variables: &[(String, String)],
+    format: ExportFormat,
+) -> Result<String, String> {
+    let mut output = String::new();
+
+    match format {
+        ExportFormat::Css => {
+            writeln!(output, ":root {{").map_err(|e| format!("Could not write line. {e}"))?;
+            for (name, value) in variables {
+                writeln!(output, "  --{name}: {value};")
+                    .map_err(|e| format!("Could not write line. {e}"))?;
+            }
+            writeln!(output, "}}").map_err(|e| format!("Could not write line. {e}"))?;
+        }
+        ExportFormat::Scss => {
+            for (name, value) in variables {
+                writeln!(output, "${name}: {value};")
+                    .map_err(|e| format!("Could not write line. {e}"))?;
+            }
+        }
+        ExportFormat::Json => {
+            let object: serde_json::Map<String, serde_json::Value> = variables
+                .iter()
+                .map(|(name, value)| (name.clone(), value.as_str().into()))
+                .collect();
+
+            writeln!(output, "{}", serde_json::Value::Object(object))
+                .map_err(|e| format!("Could not write line. {e}"))?;
+        }
+        ExportFormat::Env => {
+            for (name, value) in variables {
+                writeln!(
+                    output,
+                    "NORLYK_{}='{}'",
+                    environment_variable_name(name),
+                    value.replace('\'', r"'\''")
+                )
+                .map_err(|e| format!("Could not write line. {e}"))?;
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Converts a variable name such as `backgroundColor` or `background-color` to
+/// `BACKGROUND_COLOR`.
+fn environment_variable_name(name: &str) -> String {
+    let mut environment_name = String::new();
+
+    for (index, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 && !environment_name.ends_with('_') {
+            environment_name.push('_');
+        }
+
+        if c.is_ascii_alphanumeric() {
+            environment_name.push(c.to_ascii_uppercase());
+        } else if !environment_name.ends_with('_') {
+            environment_name.push('_');
+        }
+    }
+
+    environment_name
+}
+
+/// Reads a theme name from the first line of stdin, without surrounding whitespace.
+fn read_theme_name_from_stdin() -> Result<String, String> {
+    let mut line = String::new();
+    io::stdin()
+        .read_line(&mut line)
+        .map_err(|e| format!("Could not read the theme name from stdin: {e}"))?;
+
+    let name = line.trim();
+
+    if name.is_empty() {
+        return Err("No theme name was given on stdin".to_string());
+    }
+
+    Ok(name.to_string())
+}
+
+/// Completes the directory names of the installed themes, which unlike their names never need
+/// quoting. Nothing is completed if the themes cannot be read, since errors cannot be shown while
+/// completing.
+fn complete_theme_name(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy().to_lowercase();
+
+    // The command line being completed follows the `--` of the completion request
+    let (theme_dir, config) = completed_paths(env::args_os().skip_while(|arg| arg != "--").skip(1));
+
+    ConfigService::settings(theme_dir, config.as_deref())
+        .and_then(|settings| ThemeService::get_available_themes(&settings))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|theme| theme.dir_name().to_lowercase().starts_with(&current))
+        .map(|theme| {
+            let help = if theme.description.is_empty() {
+                theme.name.clone()
+            } else {
+                format!("{}: {}", theme.name, theme.description)
+            };
+
+            CompletionCandidate::new(theme.dir_name()).help(Some(help.into()))
+        })
+        .collect()
+}
+
+/// Gets the `--theme-dir` and `--config` of a command line, which is parsed as far as it can be,
+/// since the word being completed is usually not a valid argument yet.
+fn completed_paths(
+    words: impl IntoIterator<Item = OsString>,
+) -> (Option<PathBuf>, Option<PathBuf>) {
+    let Ok(matches) = Args::command()
+        .ignore_errors(true)
+        .try_get_matches_from(words)
+    else {
+        return (None, None);
+    };
+
+    (
+        matches.get_one::<PathBuf>("theme_dir").cloned(),
+        matches.get_one::<PathBuf>("config").cloned(),
+    )
+}
+
+/// Finds the theme with the name or directory name, or unless `exact`, the only theme starting
+/// with or containing it, reporting an error if there is no such theme or several.
+fn find_theme(output: &Output, settings: &Settings, name: &str, exact: bool) -> Option<Theme> {
+    // The name is only compared with the themes, so it may contain any character, e.g. a `/`
+    if name.trim().is_empty() {
+        output.error(
+            ErrorKind::InvalidThemeName,
+            "The theme name cannot be empty",
+        );
+        return None;
+    }
+
+    let themes = match ThemeService::get_available_themes(settings) {
+        Ok(themes) => themes,
+        Err(e) => {
+            output.error(
+                ErrorKind::ThemesUnavailable,
+                &format!("Could not get themes: {e}"),
+            );
+            return None;
+        }
+    };
+
+    let matches = match_themes(&themes, name, exact);
+
+    if let [theme] = matches.as_slice() {
+        return Some((*theme).clone());
+    }
+
+    if !matches.is_empty() {
+        let candidates: Vec<String> = matches
+            .iter()
+            .map(|theme| format!(" - {} ({})", theme.dir_name(), theme.name))
+            .collect();
+
+        output.error(
+            ErrorKind::AmbiguousThemeName,
+            &format!("{name} matches several themes:\n{}", candidates.join("\n")),
+        );
+        return None;
+    }
+
+    let mut message = format!("Could not get theme: {name}\nAvailable themes:");
+    for theme in themes {
+        message.push_str("\n - ");
+        message.push_str(&theme.dir_name());
+        if theme.name != theme.dir_name() {
+            message.push_str(" (");
+            message.push_str(&theme.name);
+            message.push(')');
+        }
+    }
+
+    output.error(ErrorKind::ThemeNotFound, &message);
+
+    None
+}
+
+/// Finds the theme whose directory name is `query`, or otherwise the themes whose name or
+/// directory name is `query` ignoring case, e.g. `Tokyo Night` for `tokyonight`. Unless `exact`,
+/// it then finds the themes whose name or directory name starts with it, or otherwise contains it,
+/// ignoring case.
+fn match_themes<'a>(themes: &'a [Theme], query: &str, exact: bool) -> Vec<&'a Theme> {
+    // Directory names are unique, unlike names, so they take precedence
+    if let Some(theme) = themes.iter().find(|theme| theme.dir_name() == query) {
+        return vec![theme];
+    }
+
+    let query = query.to_lowercase();
+    let names = |theme: &Theme| [theme.name.to_lowercase(), theme.dir_name().to_lowercase()];
+
+    let exact_matches: Vec<&Theme> = themes
+        .iter()
+        .filter(|theme| names(theme).contains(&query))
+        .collect();
+
+    if exact || !exact_matches.is_empty() {
+        return exact_matches;
+    }
+
+    let prefix_matches: Vec<&Theme> = themes
+        .iter()
+        .filter(|theme| names(theme).iter().any(|name| name.starts_with(&query)))
+        .collect();
+
+    if !prefix_matches.is_empty() {
+        return prefix_matches;
+    }
+
+    themes
+        .iter()
+        .filter(|theme| names(theme).iter().any(|name| name.contains(&query)))
+        .collect()
+}
+
+fn theme_listings(themes: &[Theme], verbose: bool, include_videos: bool) -> Vec<ThemeListing> {
+    themes
+        .iter()
+        .map(|theme| ThemeListing {
+            dir_name: theme.dir_name(),
+            name: theme.name.clone(),
+            description: theme.description.clone(),
+            wallpaper_count: verbose.then(|| theme.wallpaper_count(include_videos)),
+            tags: theme.tags.clone(),
+        })
+        .collect()
+}
+
+fn list_themes(themes: &[Theme], format: ListFormat, verbose: bool, include_videos: bool) {
+    match format {
+        ListFormat::Plain => {
+            for theme in themes {
+                if verbose {
+                    println!(
+                        "{} ({} wallpapers)",
+                        theme.name,
+                        theme.wallpaper_count(include_videos)
+                    );
+                } else {
+                    println!("{}", theme.name);
+                }
+            }
+        }
+        ListFormat::Json => {
+            match serde_json::to_string_pretty(&theme_listings(themes, verbose, include_videos)) {
+                Ok(json) => println!("{json}"),
+                Err(e) => eprintln!("Could not serialize themes: {e}"),
+            }
+        }
+        ListFormat::Csv => {
+            if verbose {
+                println!("dir_name,name,description,wallpaper_count");
+            } else {
+                println!("dir_name,name,description");
+            }
+
+            for theme in themes {
+                let row = format!(
+                    "{},{},{}",
+                    escape_csv(&theme.dir_name()),
+                    escape_csv(&theme.name),
+                    escape_csv(&theme.description)
+                );
+
+                if verbose {
+                    println!("{row},{}", theme.wallpaper_count(include_videos));
+                } else {
+                    println!("{row}");
+                }
+            }
+        }
+    }
+}
+
+/// Warns on stderr about themes without wallpapers, since setting their wallpaper fails.
+fn warn_about_missing_wallpapers(output: &Output, themes: &[Theme], include_videos: bool) {
+    for theme in themes {
+        if theme.wallpaper_count(include_videos) == 0 && theme.wallpaper_color.is_none() {
+            output.warning(&format!(
+                "Warning: {} has no wallpapers in {}",
+                theme.name,
+                theme.wallpapers_path().display()
+            ));
+        }
+    }
+}
+
+/// Warns on stderr about themes without a variables file, since they cannot be applied.
+fn warn_about_incomplete_themes(output: &Output, themes: &[Theme]) {
+    for theme in themes {
+        if !theme.is_complete() {
+            output.warning(&format!(
+                "Warning: {} is incomplete, {} does not exist",
+                theme.name,
+                theme.get_theme_variables_css_file_path().display()
+            ));
+        }
+    }
+}
+
+/// Quotes the field if it contains a comma, quote or line break, doubling any quotes inside it.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Prints a checklist of the diagnoses, returning whether all critical checks passed.
+fn doctor(output: &Output, settings: &Settings) -> bool {
+    let mut all_critical_passed = true;
+
+    for diagnosis in DoctorService::diagnose(settings) {
+        match diagnosis.result {
+            Ok(()) => println!("{} {}", output.mark(true), diagnosis.description),
+            Err(e) => {
+                all_critical_passed &= !diagnosis.critical;
+                println!("{} {}: {e}", output.mark(false), diagnosis.description);
+                println!(
+                    "  {}",
+                    Output::paint(
+                        output.color,
+                        Output::DIM,
+                        &format!("Hint: {}", diagnosis.hint)
+                    )
+                );
+            }
+        }
+    }
+
+    all_critical_passed
+}
+
+/// Prints whether the theme would be applied to each component, with the reason if it would not.
+fn print_integrations(output: &Output, settings: &Settings, options: &ApplyOptions) {
+    let integrations = IntegrationService::discover(settings, options);
+
+    if output.json {
+        let components: Vec<_> = integrations
+            .iter()
+            .map(|integration| {
+                json!({
+                    "component": integration.component.name(),
+                    "applied": integration.skip_reason.is_none(),
+                    "skip_reason": integration.skip_reason,
+                })
+            })
+            .collect();
+
+        println!("{}", json!({ "status": "ok", "components": components }));
+        return;
+    }
+
+    for integration in integrations {
+        match integration.skip_reason {
+            None => println!("{} {}", output.mark(true), integration.component),
+            Some(reason) => println!("{} {}: {reason}", output.mark(false), integration.component),
+        }
+    }
+}
+
+/// Prints the result of each verification, except in JSON mode, returning the descriptions of
+/// the verifications that failed.
+fn verify_theme(
+    output: &Output,
+    settings: &Settings,
+    theme: &Theme,
+    snapshot: &Snapshot,
+) -> Vec<String> {
+    let mut failed = Vec::new();
+
+    for verification in VerificationService::verify(settings, theme, snapshot) {
+        match verification.result {
+            Ok(()) if !output.json => println!("[ok]   {}", verification.description),
+            Ok(()) => {}
+            Err(e) => {
+                if !output.json {
+                    println!("[fail] {}: {e}", verification.description);
+                }
+                failed.push(verification.description);
+            }
+        }
+    }
+
+    failed
+}
+
+fn print_active_wallpapers(output: &Output, wallpapers: &[(String, PathBuf)]) {
+    if output.json {
+        let wallpapers: Vec<_> = wallpapers
+            .iter()
+            .map(|(monitor, path)| json!({ "monitor": monitor, "path": path }))
+            .collect();
+
+        println!("{}", json!({ "status": "ok", "wallpapers": wallpapers }));
+        return;
+    }
+
+    for (monitor, path) in wallpapers {
+        println!("{monitor}: {}", path.display());
+    }
+}
+
+/// Changes the wallpaper every `interval` until the process is interrupted. The `current` theme is
+/// resolved again on every change, so the rotation follows theme switches. No state is kept
+/// between changes, so the default handling of SIGINT and SIGTERM shuts down cleanly.
+fn rotate_wallpaper(output: &Output, settings: &Settings, interval: Duration) {
+    let mut previous_wallpaper: Option<PathBuf> = None;
+
+    loop {
+        match ThemeService::change_wallpaper_excluding(settings, previous_wallpaper.as_deref()) {
+            Ok(wallpaper) => {
+                output.success(&format!(
+                    "The wallpaper was changed to {}",
+                    wallpaper.display()
+                ));
+                previous_wallpaper = Some(wallpaper);
+            }
+            Err(e) => output.error(
+                ErrorKind::from(&e),
+                &format!("Error changing wallpaper: {e}"),
+            ),
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+/// Parses a human-readable duration such as `90s`, `15m` or `1h30m`. A number without a unit is
+/// in seconds.
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let multiplier = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return Err(format!("Invalid unit '{c}' in interval: {value}")),
+        };
+
+        let amount: u64 = number
+            .parse()
+            .map_err(|_| format!("Invalid interval: {value}"))?;
+
+        seconds = amount
+            .checked_mul(multiplier)
+            .and_then(|amount_seconds| seconds.checked_add(amount_seconds))
+            .ok_or_else(|| format!("The interval is too long: {value}"))?;
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        let amount: u64 = number
+            .parse()
+            .map_err(|_| format!("Invalid interval: {value}"))?;
+
+        seconds = seconds
+            .checked_add(amount)
+            .ok_or_else(|| format!("The interval is too long: {value}"))?;
+    }
+
+    if seconds == 0 {
+        return Err("The interval must be greater than zero".to_string());
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_interval_adds_up_the_units() {
+        assert_eq!(parse_interval("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_interval("15m"), Ok(Duration::from_mins(15)));
+        assert_eq!(parse_interval("1h30m"), Ok(Duration::from_mins(90)));
+        assert_eq!(parse_interval(" 1d "), Ok(Duration::from_hours(24)));
+    }
+
+    #[test]
+    fn parse_interval_rejects_invalid_intervals() {
+        for value in ["", "0", "0m", "m", "15x", "1.5h", "-5m"] {
+            assert!(parse_interval(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn completed_paths_reads_the_global_options_of_a_partial_command_line() {
+        let words = [
+            "norlyk",
+            "--theme-dir",
+            "/themes",
+            "theme",
+            "--config",
+            "/config.toml",
+            "kana",
+        ]
+        .map(OsString::from);
+
+        assert_eq!(
+            completed_paths(words),
+            (
+                Some(PathBuf::from("/themes")),
+                Some(PathBuf::from("/config.toml"))
+            )
+        );
+        assert_eq!(
+            completed_paths(["norlyk", "theme", ""].map(OsString::from)),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn parse_interval_rejects_intervals_that_overflow() {
+        assert!(parse_interval("18446744073709551615d").is_err());
+        assert!(parse_interval("18446744073709551615s1s").is_err());
+        assert!(parse_interval("18446744073709551615s1").is_err());
+        assert!(parse_interval("99999999999999999999").is_err());
     }
 }
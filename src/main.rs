@@ -1,75 +1,274 @@
+use ratatui::crossterm::cursor::MoveTo;
+use ratatui::crossterm::execute;
 use ratatui::style::Color;
 use ratatui::{
     DefaultTerminal,
     buffer::Buffer,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
-    layout::{Constraint, Layout, Rect},
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Flex, Layout, Rect},
     style::{Modifier, Style, Stylize},
-    text::Line,
+    text::{Line, Span},
     widgets::{
-        Block, Borders, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget,
-        Widget, Wrap,
+        Block, Borders, Clear, HighlightSpacing, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, StatefulWidget, Widget, Wrap,
     },
 };
 use std::io;
+use std::path::PathBuf;
+use theme_picker::models::apply_options::ApplyOptions;
+use theme_picker::models::color_blindness::ColorBlindness;
+use theme_picker::models::component::Component;
+use theme_picker::models::hex_color::HexColor;
+use theme_picker::models::loaded_themes::SkippedTheme;
+use theme_picker::models::settings::Settings;
 use theme_picker::models::theme::Theme;
+use theme_picker::services::config_service::ConfigService;
 use theme_picker::services::theme_service::ThemeService;
+use theme_picker::utils::clipboard::Clipboard;
+use theme_picker::utils::terminal_graphics::TerminalGraphics;
+
+/// The number of columns the thumbnail of the selected theme is scaled to.
+const THUMBNAIL_WIDTH: u16 = 16;
+
+/// The number of columns of the popup for choosing which components to apply themes to.
+const COMPONENTS_POPUP_WIDTH: u16 = 24;
+
+/// The number of columns of the popups for entering text, such as the directory name of a
+/// duplicated theme.
+const INPUT_POPUP_WIDTH: u16 = 40;
 
 fn main() -> io::Result<()> {
-    ratatui::run(|terminal| App::default().run(terminal))
+    let settings = ConfigService::settings(None, None).map_err(io::Error::other)?;
+
+    ratatui::run(|terminal| App::new(settings).run(terminal))
+}
+
+/// What is shown about the selected theme that has to be read from disk, so that it is only read
+/// again when another theme is selected instead of on every frame.
+struct SelectedThemeDetails {
+    directory_path: PathBuf,
+    /// The colors of the theme's variables, shown as swatches.
+    colors: Vec<HexColor>,
+    /// The number of wallpapers the theme has.
+    wallpaper_count: usize,
 }
 
 struct App {
     should_exit: bool,
+    /// The directories and global configuration, loaded once at startup.
+    settings: Settings,
+    /// Every available theme, whether or not it matches the tag filter.
+    all_themes: Vec<Theme>,
+    /// The themes shown in the list, which are the ones matching the tag filter.
     themes: Vec<Theme>,
     state: ListState,
+    graphics_supported: bool,
+    /// The thumbnail of the selected theme and the area to draw it in, set when rendering.
+    thumbnail: Option<(PathBuf, Rect)>,
+    /// The components that themes are applied to, kept for the rest of the session.
+    apply_options: ApplyOptions,
+    /// The selected row of the components popup, or `None` when the popup is closed.
+    components_state: Option<ListState>,
+    /// The number of visible rows in the theme list, set when rendering.
+    list_height: usize,
+    /// The directory name being entered for a duplicate of the selected theme, or `None` when
+    /// not duplicating.
+    duplicate_name: Option<String>,
+    /// The color blindness the swatches of the selected theme are simulated for.
+    simulation: ColorBlindness,
+    /// The swatch colors and wallpaper count of the theme that was selected last.
+    selected_details: Option<SelectedThemeDetails>,
+    /// A message about the last action, such as copying to the clipboard, cleared on the next key.
+    status: Option<String>,
+    /// The tags a theme must all have to be listed, or empty to list every theme.
+    tag_filter: Vec<String>,
+    /// The tags being entered for the tag filter, or `None` when not filtering.
+    tag_input: Option<String>,
 }
 
-impl Default for App {
-    fn default() -> Self {
-        let themes = ThemeService::get_available_themes().unwrap_or_else(|e| {
-            eprintln!("Could not get themes: {e}");
-            Vec::new()
-        });
+impl App {
+    fn new(settings: Settings) -> Self {
+        let (themes, status) = match ThemeService::load_themes(&settings) {
+            Ok(loaded) => (loaded.themes, Self::skipped_status(&loaded.skipped)),
+            Err(e) => (Vec::new(), Some(format!("Could not get themes: {e}"))),
+        };
 
         Self {
             should_exit: false,
+            settings,
+            all_themes: themes.clone(),
             themes,
             state: ListState::default(),
+            graphics_supported: TerminalGraphics::is_supported(),
+            thumbnail: None,
+            apply_options: ApplyOptions::default(),
+            components_state: None,
+            list_height: 0,
+            duplicate_name: None,
+            simulation: ColorBlindness::Normal,
+            selected_details: None,
+            status,
+            tag_filter: Vec::new(),
+            tag_input: None,
         }
     }
-}
 
-impl App {
     fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
-        self.select_first();
+        self.select_initial();
 
         while !self.should_exit {
             terminal.draw(|frame| frame.render_widget(&mut self, frame.area()))?;
+            self.draw_thumbnail()?;
 
             if let Event::Key(key) = event::read()? {
                 self.handle_key(key);
             }
         }
+
+        // Only a convenience for the next session, so failing to record it is not worth reporting
+        if let Some(selected_theme) = self.get_selected_theme() {
+            let _ = ThemeService::set_last_selection(&self.settings, selected_theme);
+        }
+
         Ok(())
     }
 
+    /// Selects the theme selected when the theme picker was last closed, or otherwise the current
+    /// theme, or otherwise the first theme.
+    fn select_initial(&mut self) {
+        let last_selection = ThemeService::last_selection(&self.settings);
+        let current_theme_directory = ThemeService::current_theme_directory(&self.settings);
+
+        let index = last_selection
+            .and_then(|dir_name| {
+                self.themes
+                    .iter()
+                    .position(|theme| theme.dir_name() == dir_name)
+            })
+            .or_else(|| {
+                self.themes.iter().position(|theme| {
+                    Some(&theme.directory_path) == current_theme_directory.as_ref()
+                })
+            });
+
+        match index {
+            Some(index) => self.state.select(Some(index)),
+            None => self.select_first(),
+        }
+    }
+
     fn handle_key(&mut self, key: KeyEvent) {
         if key.kind != KeyEventKind::Press {
             return;
         }
 
+        self.status = None;
+
+        if let Some(duplicate_name) = &mut self.duplicate_name {
+            match key.code {
+                KeyCode::Esc => self.duplicate_name = None,
+                KeyCode::Enter => self.duplicate_theme(),
+                KeyCode::Backspace => {
+                    duplicate_name.pop();
+                }
+                KeyCode::Char(c) => duplicate_name.push(c),
+                _ => {}
+            }
+
+            return;
+        }
+
+        if let Some(tag_input) = &mut self.tag_input {
+            match key.code {
+                KeyCode::Esc => self.tag_input = None,
+                KeyCode::Enter => {
+                    self.tag_filter = tag_input
+                        .split([' ', ','])
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    self.tag_input = None;
+                    self.filter_themes();
+                }
+                KeyCode::Backspace => {
+                    tag_input.pop();
+                }
+                KeyCode::Char(c) => tag_input.push(c),
+                _ => {}
+            }
+
+            return;
+        }
+
+        if let Some(components_state) = &mut self.components_state {
+            match key.code {
+                KeyCode::Char('c' | 'q') | KeyCode::Esc => self.components_state = None,
+                KeyCode::Char('j') | KeyCode::Down => components_state.select_next(),
+                KeyCode::Char('k') | KeyCode::Up => components_state.select_previous(),
+                KeyCode::Char(' ') | KeyCode::Enter => {
+                    if let Some(component) = components_state
+                        .selected()
+                        .and_then(|index| Component::ALL.get(index))
+                    {
+                        self.apply_options.toggle(*component);
+                    }
+                }
+                _ => {}
+            }
+
+            return;
+        }
+
         match key.code {
             KeyCode::Char('q') | KeyCode::Esc => self.should_exit = true,
             KeyCode::Char('j') | KeyCode::Down => self.select_next(),
             KeyCode::Char('k') | KeyCode::Up => self.select_previous(),
             KeyCode::Char('g') | KeyCode::Home => self.select_first(),
             KeyCode::Char('G') | KeyCode::End => self.select_last(),
+            KeyCode::PageDown => self.select_next_page(),
+            KeyCode::PageUp => self.select_previous_page(),
+            KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.select_next_page();
+            }
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.select_previous_page();
+            }
+            KeyCode::Char('o') => self.open_theme_directory(),
+            KeyCode::Char('v') => self.simulation = self.simulation.next(),
+            KeyCode::Char('y') => self.copy_dir_name(),
+            KeyCode::Char('Y') => self.copy_palette(),
+            KeyCode::Char('c') => {
+                self.components_state = Some(ListState::default().with_selected(Some(0)));
+            }
+            KeyCode::Char('t') => self.tag_input = Some(self.tag_filter.join(" ")),
+            KeyCode::Char('d') => {
+                self.duplicate_name = self
+                    .get_selected_theme()
+                    .map(|theme| format!("{}-copy", theme.dir_name()));
+            }
             KeyCode::Enter => self.toggle_theme(),
             _ => {}
         }
     }
 
+    /// Draws the selected theme's thumbnail on top of the rendered frame, since images cannot be
+    /// part of the ratatui buffer.
+    fn draw_thumbnail(&self) -> io::Result<()> {
+        if !self.graphics_supported {
+            return Ok(());
+        }
+
+        let mut stdout = io::stdout();
+        TerminalGraphics::clear(&mut stdout)?;
+
+        let Some((path, area)) = &self.thumbnail else {
+            return Ok(());
+        };
+
+        execute!(stdout, MoveTo(area.x, area.y))?;
+        TerminalGraphics::draw_png(&mut stdout, path, area.width, area.height)
+    }
+
     fn select_next(&mut self) {
         self.state.select_next();
     }
@@ -78,6 +277,25 @@ impl App {
         self.state.select_previous();
     }
 
+    /// Moves the selection down by a page of visible items, stopping at the last item.
+    fn select_next_page(&mut self) {
+        let Some(last) = self.themes.len().checked_sub(1) else {
+            return;
+        };
+        let selected = self.state.selected().unwrap_or_default();
+
+        self.state
+            .select(Some((selected + self.list_height.max(1)).min(last)));
+    }
+
+    /// Moves the selection up by a page of visible items, stopping at the first item.
+    fn select_previous_page(&mut self) {
+        let selected = self.state.selected().unwrap_or_default();
+
+        self.state
+            .select(Some(selected.saturating_sub(self.list_height.max(1))));
+    }
+
     fn select_first(&mut self) {
         self.state.select_first();
     }
@@ -91,15 +309,153 @@ impl App {
             return;
         };
 
-        if let Err(e) = ThemeService::set_current_theme(selected_theme) {
-            eprintln!("Failed to set the theme: {}\n{}", selected_theme.name, e);
+        if !selected_theme.is_complete() {
+            self.status = Some(format!(
+                "{} is incomplete, add {} to apply it",
+                selected_theme.name,
+                selected_theme.get_theme_variables_css_file_path().display()
+            ));
+            return;
+        }
+
+        self.status = match ThemeService::set_current_theme(
+            &self.settings,
+            selected_theme,
+            &self.apply_options,
+        ) {
+            Ok(()) => None,
+            Err(e) => Some(format!("Failed to set {}: {e}", selected_theme.name)),
+        };
+    }
+
+    /// Copies the directory name of the selected theme to the clipboard, e.g. for `norlyk theme`.
+    fn copy_dir_name(&mut self) {
+        let Some(selected_theme) = self.get_selected_theme() else {
+            return;
+        };
+
+        let dir_name = selected_theme.dir_name();
+
+        self.status = Some(match Clipboard::copy(&dir_name) {
+            Ok(()) => format!("Copied {dir_name}"),
+            Err(e) => format!("Could not copy: {e}"),
+        });
+    }
+
+    /// Copies the resolved variables of the selected theme to the clipboard as a JSON object.
+    fn copy_palette(&mut self) {
+        let Some(selected_theme) = self.get_selected_theme() else {
+            return;
+        };
+
+        let result = ThemeService::get_variables(selected_theme).and_then(|variables| {
+            let palette: serde_json::Map<String, serde_json::Value> = variables
+                .into_iter()
+                .map(|(name, value)| (name, value.into()))
+                .collect();
+
+            Clipboard::copy(&serde_json::Value::Object(palette).to_string())
+        });
+
+        self.status = Some(match result {
+            Ok(()) => format!("Copied the palette of {}", selected_theme.name),
+            Err(e) => format!("Could not copy: {e}"),
+        });
+    }
+
+    /// Duplicates the selected theme under the entered directory name, and selects the copy.
+    fn duplicate_theme(&mut self) {
+        let Some(duplicate_name) = self.duplicate_name.take() else {
+            return;
+        };
+
+        let Some(selected_theme) = self.get_selected_theme() else {
+            return;
+        };
+
+        let duplicate_path = match ThemeService::duplicate_theme(selected_theme, &duplicate_name) {
+            Ok(path) => path,
+            Err(e) => {
+                self.status = Some(format!("Failed to duplicate the theme: {e}"));
+                return;
+            }
+        };
+
+        match ThemeService::load_themes(&self.settings) {
+            Ok(loaded) => {
+                self.all_themes = loaded.themes;
+                self.status = Self::skipped_status(&loaded.skipped);
+            }
+            Err(e) => self.status = Some(format!("Could not get themes: {e}")),
+        }
+
+        self.filter_themes();
+
+        let index = self
+            .themes
+            .iter()
+            .position(|theme| theme.directory_path == duplicate_path);
+        self.state.select(index);
+
+        if self.status.is_none() {
+            self.status = Some(format!("Duplicated the theme as {duplicate_name}"));
+        }
+    }
+
+    /// Describes the themes that could not be loaded for the status line, or `None` if there are
+    /// none. The first one is named, so that a broken theme can be found.
+    fn skipped_status(skipped: &[SkippedTheme]) -> Option<String> {
+        match skipped {
+            [] => None,
+            [skipped_theme] => Some(skipped_theme.to_string()),
+            [skipped_theme, rest @ ..] => {
+                Some(format!("{skipped_theme} ({} more skipped)", rest.len()))
+            }
+        }
+    }
+
+    /// Lists the themes that have all the tags of the tag filter, keeping the selected theme
+    /// selected if it is still listed.
+    fn filter_themes(&mut self) {
+        let selected_path = self
+            .get_selected_theme()
+            .map(|theme| theme.directory_path.clone());
+
+        self.themes = self
+            .all_themes
+            .iter()
+            .filter(|theme| theme.matches_tags(&self.tag_filter, false))
+            .cloned()
+            .collect();
+
+        match self
+            .themes
+            .iter()
+            .position(|theme| Some(&theme.directory_path) == selected_path.as_ref())
+        {
+            Some(index) => self.state.select(Some(index)),
+            None => self.select_first(),
+        }
+    }
+
+    fn open_theme_directory(&mut self) {
+        let Some(selected_theme) = self.get_selected_theme() else {
+            return;
+        };
+
+        if let Err(e) = ThemeService::open_directory(selected_theme) {
+            self.status = Some(format!("Failed to open the theme directory: {e}"));
         }
     }
 }
 
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let title = Line::from(" Theme Picker ");
+        let title = if self.tag_filter.is_empty() {
+            Line::from(" Theme Picker ")
+        } else {
+            Line::from(format!(" Theme Picker ({}) ", self.tag_filter.join(", ")))
+        };
 
         let instructions = Line::from(vec![
             " Use ".into(),
@@ -107,6 +463,18 @@ impl Widget for &mut App {
             " to go top/bottom, ".into(),
             "enter".blue().bold(),
             " to select, ".into(),
+            "o".blue().bold(),
+            " to open, ".into(),
+            "c".blue().bold(),
+            " to choose components, ".into(),
+            "d".blue().bold(),
+            " to duplicate, ".into(),
+            "t".blue().bold(),
+            " to filter by tags, ".into(),
+            "v".blue().bold(),
+            " to simulate color blindness, ".into(),
+            "y/Y".blue().bold(),
+            " to copy name/palette, ".into(),
             "q ".blue().bold(),
             " to quit".into(),
         ]);
@@ -124,12 +492,36 @@ impl Widget for &mut App {
         self.render_list(list_area, buf);
         self.render_info(info_area, buf);
         block.render(area, buf);
+
+        if self.components_state.is_some() {
+            self.render_components(area, buf);
+        }
+
+        if let Some(duplicate_name) = self.duplicate_name.clone() {
+            self.render_input(area, buf, " Duplicate as ", &duplicate_name);
+        }
+
+        if let Some(tag_input) = self.tag_input.clone() {
+            self.render_input(area, buf, " Filter by tags ", &tag_input);
+        }
     }
 }
 
 impl App {
     fn render_list(&mut self, area: Rect, buf: &mut Buffer) {
         let items: Vec<ListItem> = self.themes.iter().map(ListItem::from).collect();
+        let visible_count = usize::from(area.height);
+        self.list_height = visible_count;
+
+        // Only show the scrollbar when the list does not fit
+        let (list_area, scrollbar_area) = if self.themes.len() > visible_count {
+            let [list_area, scrollbar_area] =
+                Layout::horizontal([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+
+            (list_area, Some(scrollbar_area))
+        } else {
+            (area, None)
+        };
 
         let list = List::new(items)
             .highlight_style(Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD))
@@ -138,7 +530,21 @@ impl App {
 
         // We need to disambiguate this trait method as both `Widget` and `StatefulWidget` share the
         // same method name `render`.
-        StatefulWidget::render(list, area, buf, &mut self.state);
+        StatefulWidget::render(list, list_area, buf, &mut self.state);
+
+        if let Some(scrollbar_area) = scrollbar_area {
+            // Positions are offsets of the first visible item, of which the last shows the end
+            let mut scrollbar_state = ScrollbarState::new(self.themes.len() - visible_count + 1)
+                .viewport_content_length(visible_count)
+                .position(self.state.offset());
+
+            StatefulWidget::render(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight),
+                scrollbar_area,
+                buf,
+                &mut scrollbar_state,
+            );
+        }
     }
 
     fn get_selected_theme(&self) -> Option<&Theme> {
@@ -147,17 +553,180 @@ impl App {
         Some(&self.themes[index])
     }
 
-    fn render_info(&self, area: Rect, buf: &mut Buffer) {
-        let Some(selected_theme) = self.get_selected_theme() else {
+    fn render_info(&mut self, area: Rect, buf: &mut Buffer) {
+        self.thumbnail = None;
+        self.update_selected_details();
+
+        let (Some(selected_theme), Some(details)) =
+            (self.get_selected_theme(), &self.selected_details)
+        else {
             return;
         };
 
-        let info = &selected_theme.description;
-        let block = Block::new().borders(Borders::ALL);
+        // Applying a theme without wallpapers fails to set the wallpaper
+        let wallpaper_count = match details.wallpaper_count {
+            0 if selected_theme.wallpaper_color.is_some() => Line::from(" Solid color wallpaper "),
+            0 => Line::from(" No wallpapers ".red()),
+            1 => Line::from(" 1 wallpaper "),
+            count => Line::from(format!(" {count} wallpapers ")),
+        };
+
+        let block = Block::new()
+            .borders(Borders::ALL)
+            .title(self.swatches())
+            .title_bottom(
+                Line::from(
+                    self.status
+                        .as_deref()
+                        .map(|status| format!(" {status} "))
+                        .unwrap_or_default(),
+                )
+                .left_aligned(),
+            )
+            .title_bottom(wallpaper_count.right_aligned());
+        let inner = block.inner(area);
+
+        // Other formats are reported as an issue of the theme when applying it
+        let thumbnail_path = selected_theme
+            .thumbnail_path
+            .as_ref()
+            .filter(|path| self.graphics_supported && TerminalGraphics::is_png(path));
+
+        let (thumbnail, info_area) = if let Some(thumbnail_path) = thumbnail_path {
+            let [thumbnail_area, info_area] =
+                Layout::horizontal([Constraint::Length(THUMBNAIL_WIDTH), Constraint::Fill(1)])
+                    .spacing(1)
+                    .areas(inner);
 
-        Paragraph::new(info.as_str())
+            (Some((thumbnail_path.clone(), thumbnail_area)), info_area)
+        } else {
+            (None, inner)
+        };
+
+        Paragraph::new(selected_theme.description.as_str())
             .wrap(Wrap { trim: false })
-            .block(block)
-            .render(area, buf);
+            .render(info_area, buf);
+
+        block.render(area, buf);
+
+        self.thumbnail = thumbnail;
+    }
+
+    /// Reads the colors of the selected theme for its swatches and counts its wallpapers, unless
+    /// they were already read. A theme whose variables cannot be read has no colors.
+    fn update_selected_details(&mut self) {
+        let Some(selected_theme) = self.get_selected_theme() else {
+            self.selected_details = None;
+            return;
+        };
+
+        if self
+            .selected_details
+            .as_ref()
+            .is_some_and(|details| details.directory_path == selected_theme.directory_path)
+        {
+            return;
+        }
+
+        let colors = ThemeService::get_variables(selected_theme)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|(_, value)| HexColor::try_from(value).ok())
+            .collect();
+
+        self.selected_details = Some(SelectedThemeDetails {
+            directory_path: selected_theme.directory_path.clone(),
+            colors,
+            wallpaper_count: selected_theme.wallpaper_count(self.settings.config.wallpaper.video),
+        });
+    }
+
+    /// Builds a swatch for each color of the selected theme, simulated for the selected color
+    /// blindness.
+    fn swatches(&self) -> Line<'static> {
+        let Some(details) = &self.selected_details else {
+            return Line::default();
+        };
+
+        let mut spans: Vec<Span<'static>> = details
+            .colors
+            .iter()
+            .filter_map(HexColor::rgb)
+            .map(|rgb| "  ".bg(Color::from(&HexColor::from(self.simulation.simulate(rgb)))))
+            .collect();
+
+        if self.simulation != ColorBlindness::Normal {
+            spans.push(format!(" {} ", self.simulation).into());
+        }
+
+        Line::from(spans)
+    }
+
+    fn render_components(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(components_state) = &mut self.components_state else {
+            return;
+        };
+
+        // The thumbnail is drawn on top of everything, so it would cover the popup
+        self.thumbnail = None;
+
+        let height = u16::try_from(Component::ALL.len())
+            .unwrap_or(u16::MAX)
+            .saturating_add(2);
+
+        let [popup_area] = Layout::horizontal([Constraint::Length(COMPONENTS_POPUP_WIDTH)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [popup_area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(popup_area);
+
+        let items: Vec<ListItem> = Component::ALL
+            .iter()
+            .map(|component| {
+                let checkbox = if self.apply_options.is_enabled(*component) {
+                    "[x]"
+                } else {
+                    "[ ]"
+                };
+
+                ListItem::new(format!("{checkbox} {component}"))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::new()
+                    .borders(Borders::ALL)
+                    .title(Line::from(" Components ").centered()),
+            )
+            .highlight_style(Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD))
+            .highlight_symbol(">")
+            .highlight_spacing(HighlightSpacing::Always);
+
+        Clear.render(popup_area, buf);
+        StatefulWidget::render(list, popup_area, buf, components_state);
+    }
+
+    /// Renders a popup with the text being entered, followed by a cursor.
+    fn render_input(&mut self, area: Rect, buf: &mut Buffer, title: &str, input: &str) {
+        // The thumbnail is drawn on top of everything, so it would cover the popup
+        self.thumbnail = None;
+
+        let [popup_area] = Layout::horizontal([Constraint::Length(INPUT_POPUP_WIDTH)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [popup_area] = Layout::vertical([Constraint::Length(3)])
+            .flex(Flex::Center)
+            .areas(popup_area);
+
+        let paragraph = Paragraph::new(Line::from(vec![input.into(), "_".blue()])).block(
+            Block::new()
+                .borders(Borders::ALL)
+                .title(Line::from(title).centered()),
+        );
+
+        Clear.render(popup_area, buf);
+        paragraph.render(popup_area, buf);
     }
 }
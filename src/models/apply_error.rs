@@ -0,0 +1,26 @@
+use std::fmt::{Display, Formatter};
+
+/// Error returned when a theme could not be fully applied.
+#[derive(Debug, Clone)]
+pub enum ApplyError {
+    /// The theme was not applied to anything, e.g. because its variables could not be read.
+    Failed(String),
+    /// The theme was applied, or at least made the current theme, but some of the steps failed.
+    /// Contains the error of each failed step.
+    Partial(Vec<String>),
+}
+
+impl Display for ApplyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApplyError::Failed(error) => write!(f, "{error}"),
+            ApplyError::Partial(errors) => {
+                write!(f, "The theme was applied, but some steps failed:")?;
+                for error in errors {
+                    write!(f, "\n - {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::models::component::Component;
+use crate::models::config::WaybarReload;
+use std::path::PathBuf;
+
+/// Options controlling how a theme is applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // Independent command line flags, not a state machine
+pub struct ApplyOptions {
+    /// The components the theme is applied to. Every other component is left untouched.
+    pub components: Vec<Component>,
+    /// Copies the theme into a real `current` directory instead of symlinking it, for themes on
+    /// removable drives and tools that do not follow symbolic links.
+    pub copy_current: bool,
+    /// Writes the variables sorted by name instead of in the order they are declared, so that the
+    /// generated files do not change when the variables are reordered.
+    pub sort_variables: bool,
+    /// Prints the path and contents of every generated file to stderr.
+    pub verbose: bool,
+    /// Writes the generated configuration files under this directory instead of the home
+    /// directory, keeping their paths relative to it, and leaves the running applications, the
+    /// GTK settings, the wallpaper and the current theme untouched, without running the hooks.
+    pub output_dir: Option<PathBuf>,
+    /// Writes the generated configuration files to their usual locations, but leaves the running
+    /// applications, the GTK settings and the wallpaper untouched, e.g. on a machine without
+    /// Hyprland.
+    pub no_reload: bool,
+    /// Overrides how Waybar is reloaded, instead of the configured [`WaybarReload`].
+    pub waybar_reload: Option<WaybarReload>,
+}
+
+impl ApplyOptions {
+    #[must_use]
+    pub fn is_enabled(&self, component: Component) -> bool {
+        self.components.contains(&component)
+    }
+
+    /// Enables the component if it is disabled, and disables it if it is enabled.
+    pub fn toggle(&mut self, component: Component) {
+        if self.is_enabled(component) {
+            self.components.retain(|c| *c != component);
+        } else {
+            self.components.push(component);
+        }
+    }
+}
+
+impl Default for ApplyOptions {
+    /// Applies the theme to every component, symlinking the current theme.
+    fn default() -> Self {
+        Self {
+            components: Component::ALL.to_vec(),
+            copy_current: false,
+            sort_variables: false,
+            verbose: false,
+            output_dir: None,
+            no_reload: false,
+            waybar_reload: None,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::models::hex_color::HexColor;
+use clap::ValueEnum;
+use std::fmt::{Display, Formatter};
+
+/// A kind of color blindness that colors can be simulated for, to preview how a palette looks to
+/// people with it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorBlindness {
+    /// Colors are shown as they are.
+    #[default]
+    Normal,
+    /// No red cones.
+    Protanopia,
+    /// No green cones.
+    Deuteranopia,
+    /// No blue cones.
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    /// Every kind, in the order they are cycled through.
+    pub const ALL: [ColorBlindness; 4] = [
+        ColorBlindness::Normal,
+        ColorBlindness::Protanopia,
+        ColorBlindness::Deuteranopia,
+        ColorBlindness::Tritanopia,
+    ];
+
+    /// Gets the kind after this one, starting over after the last.
+    #[must_use]
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Simulates how the color looks with this kind of color blindness, using the matrices by
+    /// Machado, Oliveira and Fernandes (2009) at full severity, applied to linear RGB.
+    #[must_use]
+    pub fn simulate(self, (red, green, blue): (u8, u8, u8)) -> (u8, u8, u8) {
+        let matrix = match self {
+            ColorBlindness::Normal => return (red, green, blue),
+            ColorBlindness::Protanopia => [
+                [0.152_286, 1.052_583, -0.204_868],
+                [0.114_503, 0.786_281, 0.099_216],
+                [-0.003_882, -0.048_116, 1.051_998],
+            ],
+            ColorBlindness::Deuteranopia => [
+                [0.367_322, 0.860_646, -0.227_968],
+                [0.280_085, 0.672_501, 0.047_413],
+                [-0.011_820, 0.042_940, 0.968_881],
+            ],
+            ColorBlindness::Tritanopia => [
+                [1.255_528, -0.076_749, -0.178_779],
+                [-0.078_411, 0.930_809, 0.147_602],
+                [0.004_733, 0.691_367, 0.303_900],
+            ],
+        };
+
+        let linear = [red, green, blue].map(HexColor::linearize);
+        let [red, green, blue] = matrix.map(|row| {
+            Self::delinearize(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2])
+        });
+
+        (red, green, blue)
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn delinearize(channel: f64) -> u8 {
+        let channel = channel.clamp(0.0, 1.0);
+        let channel = if channel <= 0.003_130_8 {
+            channel * 12.92
+        } else {
+            1.055 * channel.powf(1.0 / 2.4) - 0.055
+        };
+
+        // The channel is clamped above, so the result is always within 0..=255
+        (channel * 255.0).round() as u8
+    }
+}
+
+impl Display for ColorBlindness {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ColorBlindness::Normal => "Normal",
+            ColorBlindness::Protanopia => "Protanopia",
+            ColorBlindness::Deuteranopia => "Deuteranopia",
+            ColorBlindness::Tritanopia => "Tritanopia",
+        };
+
+        write!(f, "{name}")
+    }
+}
@@ -0,0 +1,51 @@
+use clap::ValueEnum;
+use std::fmt::{Display, Formatter};
+
+/// A part of the system that a theme is applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Component {
+    #[value(alias = "hypr")]
+    Hyprland,
+    Kitty,
+    Wezterm,
+    Waybar,
+    Btop,
+    Gtk,
+    #[value(alias = "nvim")]
+    Neovim,
+    Wallpaper,
+}
+
+impl Component {
+    /// Every component, in the order they are applied.
+    pub const ALL: [Component; 8] = [
+        Component::Hyprland,
+        Component::Kitty,
+        Component::Wezterm,
+        Component::Waybar,
+        Component::Btop,
+        Component::Gtk,
+        Component::Neovim,
+        Component::Wallpaper,
+    ];
+
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Component::Hyprland => "Hyprland",
+            Component::Kitty => "Kitty",
+            Component::Wezterm => "WezTerm",
+            Component::Waybar => "Waybar",
+            Component::Btop => "btop",
+            Component::Gtk => "GTK",
+            Component::Neovim => "Neovim",
+            Component::Wallpaper => "Wallpaper",
+        }
+    }
+}
+
+impl Display for Component {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The global configuration, read from `$XDG_CONFIG_HOME/norlyk/config.toml`. Every field has a
+/// default, which is used when the field or the whole file is missing.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub paths: PathsConfig,
+    pub hypr: HyprConfig,
+    pub wallpaper: WallpaperConfig,
+    pub waybar: WaybarConfig,
+}
+
+/// Overrides of where the generated configuration files are written. Paths may start with `~`
+/// and contain environment variables such as `$XDG_CONFIG_HOME`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PathsConfig {
+    /// The generated Hyprland style variables file.
+    pub hypr: Option<String>,
+    /// The directory containing Kitty's `theme-template.conf` and the generated `theme.conf`.
+    pub kitty: Option<String>,
+    /// The compiled Waybar stylesheet.
+    pub waybar: Option<String>,
+    /// The directory containing the `current` symlink and the other state of the theme picker,
+    /// e.g. `$XDG_STATE_HOME/norlyk`, so that the theme directory can be read-only. Defaults to
+    /// the theme directory.
+    pub state: Option<String>,
+}
+
+/// Settings for how Hyprland picks up the theme.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HyprConfig {
+    /// Also set the options in `options` whose variables changed in the running Hyprland with
+    /// `hyprctl keyword`, instead of only writing the style variables file. Falls back to
+    /// `hyprctl reload` if Hyprland rejects an option.
+    pub live: bool,
+    /// The Hyprland options set from a variable when `live` is enabled, e.g.
+    /// `"general:col.active_border" = "activeBorderColor"`.
+    pub options: BTreeMap<String, String>,
+}
+
+/// Settings for how wallpapers are selected and set.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WallpaperConfig {
+    /// Also select the videos in a theme's `wallpapers/` directory, which are played with
+    /// `mpvpaper` instead of being set with hyprpaper.
+    pub video: bool,
+    /// How many seconds to keep trying to set the wallpaper before giving up. Defaults to 15.
+    pub timeout: Option<u64>,
+    /// Select a wallpaper for each monitor, instead of showing the same one on every monitor.
+    pub per_monitor: bool,
+}
+
+/// Settings for how Waybar picks up a new stylesheet.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WaybarConfig {
+    pub reload: WaybarReload,
+    /// The command compiling the stylesheet, with its arguments, e.g. `dart-sass --no-source-map`.
+    /// It is split the way a shell would, so quoted arguments can contain spaces. The input and
+    /// output paths are appended. Defaults to `sass --no-source-map`.
+    pub sass: Option<String>,
+}
+
+/// How Waybar is made to load the compiled stylesheet.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WaybarReload {
+    /// Stop and start Waybar.
+    #[default]
+    Restart,
+    /// Send `SIGUSR2` to Waybar, which reloads its configuration and stylesheet while running.
+    Signal,
+    /// Only write the stylesheet, for setups where something else watches it and reloads Waybar.
+    None,
+}
@@ -1,22 +1,266 @@
 use crate::models::rgba_color::RGBAColor;
-use easy_color::Hex;
+use easy_color::{Hex, RGBA};
+use ratatui::style::Color;
 
 /// Wrapper for `easy_color`'s `Hex`, so that traits can be implemented
 pub struct HexColor(pub Hex);
 
+impl HexColor {
+    /// The contrast ratio WCAG requires for normal text.
+    pub const MIN_TEXT_CONTRAST: f64 = 4.5;
+
+    /// Parses the digits of a `#rgb` or `#rrggbb` color, expanding the shorthand.
+    fn parse_hex_digits(digits: &str) -> Option<Self> {
+        let expanded = match digits.len() {
+            3 => digits.chars().flat_map(|c| [c, c]).collect(),
+            6 => digits.to_string(),
+            _ => return None,
+        };
+
+        let component = |index: usize| u8::from_str_radix(expanded.get(index..index + 2)?, 16).ok();
+        let rgba: RGBA = (component(0)?, component(2)?, component(4)?, 1f32)
+            .try_into()
+            .ok()?;
+
+        Some(HexColor(rgba.into()))
+    }
+
+    /// Gets the red, green, and blue components of the color.
+    #[must_use]
+    pub fn rgb(&self) -> Option<(u8, u8, u8)> {
+        let hex = self.0.to_string();
+        let digits = hex.trim_start_matches('#');
+        let component = |index: usize| u8::from_str_radix(digits.get(index..index + 2)?, 16).ok();
+
+        Some((component(0)?, component(2)?, component(4)?))
+    }
+
+    /// Gets the relative luminance of the color as defined by WCAG, from 0.0 for black to 1.0 for
+    /// white.
+    #[must_use]
+    pub fn relative_luminance(&self) -> Option<f64> {
+        let (red, green, blue) = self.rgb()?;
+
+        Some(
+            0.2126 * Self::linearize(red)
+                + 0.7152 * Self::linearize(green)
+                + 0.0722 * Self::linearize(blue),
+        )
+    }
+
+    /// Gets the WCAG contrast ratio between the colors, from 1.0 for the same luminance to 21.0
+    /// for black and white.
+    #[must_use]
+    pub fn contrast_ratio(&self, other: &HexColor) -> Option<f64> {
+        let luminance = self.relative_luminance()?;
+        let other_luminance = other.relative_luminance()?;
+
+        Some((luminance.max(other_luminance) + 0.05) / (luminance.min(other_luminance) + 0.05))
+    }
+
+    /// Gets black or white, whichever contrasts more with the color. The contrast is always at
+    /// least 4.58:1, which is reached for a luminance of about 0.18, where black and white contrast
+    /// equally, so it meets the [4.5:1](Self::MIN_TEXT_CONTRAST) that WCAG requires for text.
+    #[must_use]
+    pub fn contrast_color(&self) -> Option<HexColor> {
+        let black = Self::parse_hex_digits("000000")?;
+        let white = Self::parse_hex_digits("ffffff")?;
+
+        if self.contrast_ratio(&black)? >= self.contrast_ratio(&white)? {
+            Some(black)
+        } else {
+            Some(white)
+        }
+    }
+
+    /// Gets the accent if it contrasts with the color by at least
+    /// [4.5:1](Self::MIN_TEXT_CONTRAST). Otherwise, the accent is darkened or lightened towards
+    /// the [contrast color](Self::contrast_color), in steps of 5%, until it does.
+    #[must_use]
+    pub fn readable_accent(&self, accent: &HexColor) -> Option<HexColor> {
+        let target = self.contrast_color()?;
+
+        for weight in (0..=100).step_by(5) {
+            let mixed = accent.mix(&target, weight)?;
+
+            if self.contrast_ratio(&mixed)? >= Self::MIN_TEXT_CONTRAST {
+                return Some(mixed);
+            }
+        }
+
+        Some(target)
+    }
+
+    /// Mixes the color with another color, taking `weight` percent of the other color.
+    fn mix(&self, other: &HexColor, weight: u16) -> Option<HexColor> {
+        let (red, green, blue) = self.rgb()?;
+        let (other_red, other_green, other_blue) = other.rgb()?;
+        let channel = |own: u8, other: u8| {
+            let mixed = (u16::from(own) * (100 - weight) + u16::from(other) * weight + 50) / 100;
+            u8::try_from(mixed).ok()
+        };
+
+        Some(HexColor::from((
+            channel(red, other_red)?,
+            channel(green, other_green)?,
+            channel(blue, other_blue)?,
+        )))
+    }
+
+    /// Converts a gamma encoded sRGB channel to linear light, from 0.0 to 1.0.
+    #[must_use]
+    pub fn linearize(channel: u8) -> f64 {
+        let channel = f64::from(channel) / 255.0;
+
+        if channel <= 0.040_45 {
+            channel / 12.92
+        } else {
+            ((channel + 0.055) / 1.055).powf(2.4)
+        }
+    }
+}
+
 impl TryFrom<&String> for HexColor {
     type Error = String;
 
     fn try_from(value: &String) -> Result<Self, Self::Error> {
+        value.as_str().try_into()
+    }
+}
+
+impl TryFrom<&str> for HexColor {
+    type Error = String;
+
+    /// Parses `#rgb`, `#rrggbb` and `rgba(red, green, blue, alpha)` colors.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if let Some(digits) = value.strip_prefix('#') {
+            return Self::parse_hex_digits(digits)
+                .ok_or_else(|| format!("Invalid color '{value}', expected #rgb or #rrggbb"));
+        }
+
         let rgba_color: RGBAColor = value.try_into()?;
-        let hex_value: Hex = rgba_color.0.into();
+        let hex_value: Hex = rgba_color.rgba.into();
 
         Ok(HexColor(hex_value))
     }
 }
 
+impl From<(u8, u8, u8)> for HexColor {
+    /// Creates the opaque color with the red, green and blue components.
+    fn from((red, green, blue): (u8, u8, u8)) -> Self {
+        let rgba: RGBA = (red, green, blue, 1f32).try_into().unwrap();
+        HexColor(rgba.into())
+    }
+}
+
+impl From<&HexColor> for Color {
+    /// Converts to a truecolor terminal color, or the terminal's default color if the hex value
+    /// cannot be read.
+    fn from(value: &HexColor) -> Self {
+        value.rgb().map_or(Color::Reset, |(red, green, blue)| {
+            Color::Rgb(red, green, blue)
+        })
+    }
+}
+
 impl From<HexColor> for String {
     fn from(value: HexColor) -> Self {
         value.0.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(value: &str) -> HexColor {
+        HexColor::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn relative_luminance_ranges_from_black_to_white() {
+        assert!(color("#000000").relative_luminance().unwrap().abs() < 1e-9);
+        assert!((color("#ffffff").relative_luminance().unwrap() - 1.0).abs() < 1e-9);
+        assert!((color("#808080").relative_luminance().unwrap() - 0.2159).abs() < 1e-4);
+    }
+
+    #[test]
+    fn linearize_uses_the_linear_segment_for_dark_channels() {
+        assert!((HexColor::linearize(10) - 10.0 / 255.0 / 12.92).abs() < 1e-9);
+        assert!((HexColor::linearize(255) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contrast_ratio_is_symmetric_and_ranges_from_1_to_21() {
+        let black = color("#000");
+        let white = color("#fff");
+
+        assert!((black.contrast_ratio(&white).unwrap() - 21.0).abs() < 1e-9);
+        assert!((white.contrast_ratio(&black).unwrap() - 21.0).abs() < 1e-9);
+        assert!((white.contrast_ratio(&white).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn contrast_color_meets_the_text_contrast_for_every_color() {
+        for red in (0..=255).step_by(15) {
+            for green in (0..=255).step_by(15) {
+                for blue in (0..=255).step_by(15) {
+                    let background = color(&format!("#{red:02x}{green:02x}{blue:02x}"));
+                    let foreground = background.contrast_color().unwrap();
+
+                    assert!(
+                        background.contrast_ratio(&foreground).unwrap()
+                            >= HexColor::MIN_TEXT_CONTRAST
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn contrast_color_picks_white_on_dark_and_black_on_light_colors() {
+        assert_eq!(
+            String::from(color("#1e1e2e").contrast_color().unwrap()),
+            "#FFFFFF"
+        );
+        assert_eq!(
+            String::from(color("#eff1f5").contrast_color().unwrap()),
+            "#000000"
+        );
+    }
+
+    #[test]
+    fn converts_to_a_truecolor_terminal_color() {
+        assert_eq!(Color::from(&color("#1f1f28")), Color::Rgb(31, 31, 40));
+        assert_eq!(
+            Color::from(&color("rgba(220, 215, 186, 0.5)")),
+            Color::Rgb(220, 215, 186)
+        );
+    }
+
+    #[test]
+    fn converts_from_the_red_green_and_blue_components() {
+        assert_eq!(String::from(HexColor::from((31, 31, 40))), "#1F1F28");
+    }
+
+    #[test]
+    fn readable_accent_keeps_accents_with_enough_contrast() {
+        let accent = color("#f5c2e7");
+
+        assert_eq!(
+            String::from(color("#1e1e2e").readable_accent(&accent).unwrap()),
+            "#F5C2E7"
+        );
+    }
+
+    #[test]
+    fn readable_accent_darkens_accents_without_enough_contrast() {
+        let background = color("#eff1f5");
+        let accent = background.readable_accent(&color("#f5c2e7")).unwrap();
+
+        assert!(background.contrast_ratio(&accent).unwrap() >= HexColor::MIN_TEXT_CONTRAST);
+        assert!(
+            accent.relative_luminance().unwrap() < color("#f5c2e7").relative_luminance().unwrap()
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use crate::models::theme::Theme;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// The themes in the theme directory, and the directories that look like themes but could not be
+/// loaded.
+#[derive(Debug, Clone, Default)]
+pub struct LoadedThemes {
+    /// The loaded themes, sorted by name.
+    pub themes: Vec<Theme>,
+    pub skipped: Vec<SkippedTheme>,
+}
+
+/// A directory with a metadata file that could not be loaded as a theme, e.g. because the
+/// metadata is invalid.
+#[derive(Debug, Clone)]
+pub struct SkippedTheme {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+impl Display for SkippedTheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Skipped the theme in {}. {}",
+            self.path.display(),
+            self.error
+        )
+    }
+}
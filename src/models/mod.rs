@@ -1,3 +1,14 @@
+pub mod apply_error;
+pub mod apply_options;
+pub mod color_blindness;
+pub mod component;
+pub mod config;
 pub mod hex_color;
+pub mod loaded_themes;
 pub mod rgba_color;
+pub mod settings;
 pub mod theme;
+pub mod theme_issue;
+pub mod theme_metadata;
+pub mod wallpaper_error;
+pub mod wallpaper_strategy;
@@ -1,30 +1,129 @@
 use easy_color::RGBA;
 
 /// Wrapper for `easy_color`'s `RGBA`, so that traits can be implemented
-pub struct RGBAColor(pub RGBA);
+pub struct RGBAColor {
+    /// The color, always opaque. In Kitty, we force the alpha value to be '1'
+    pub rgba: RGBA,
+    /// The alpha value of the color, clamped to `0.0..=1.0`
+    pub alpha: f32,
+}
+
+impl RGBAColor {
+    /// Gets the red, green, and blue components of the color.
+    #[must_use]
+    pub fn rgb(&self) -> (u8, u8, u8) {
+        (self.rgba.red(), self.rgba.green(), self.rgba.blue())
+    }
+
+    /// Gets the alpha value as the last byte of an 8-digit hex color, rounded to the nearest value.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn alpha_byte(&self) -> u8 {
+        (self.alpha * 255.0).round() as u8
+    }
+
+    /// Gets the color as `#RRGGBB`, or as `#RRGGBBAA` if it is not opaque.
+    #[must_use]
+    pub fn hex(&self) -> String {
+        let (red, green, blue) = self.rgb();
+
+        match self.alpha_byte() {
+            u8::MAX => format!("#{red:02X}{green:02X}{blue:02X}"),
+            alpha => format!("#{red:02X}{green:02X}{blue:02X}{alpha:02X}"),
+        }
+    }
+}
 
 impl TryFrom<&String> for RGBAColor {
     type Error = String;
 
     fn try_from(value: &String) -> Result<Self, Self::Error> {
-        let rgba_tuple: (u8, u8, u8, f32) = {
-            let parts: Vec<u8> = value
-                .replace("rgba(", "")
-                .replace(')', "")
-                .split(',')
-                .filter_map(|s| s.trim().parse::<u8>().ok())
-                .collect();
-
-            // We don't care about the 4th part (the alpha value). In Kitty, we force this to be '1'
-            if parts.len() < 3 {
-                return Err(format!("Invalid string: {value}"));
-            }
-
-            (parts[0], parts[1], parts[2], 1f32)
+        value.as_str().try_into()
+    }
+}
+
+impl TryFrom<&str> for RGBAColor {
+    type Error = String;
+
+    /// Parses `rgba(red, green, blue, alpha)`, clamping an alpha outside `0.0..=1.0`. Without an
+    /// alpha, the color is opaque.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let inner = value.replace("rgba(", "").replace(')', "");
+        let parts: Vec<&str> = inner.split(',').map(str::trim).collect();
+
+        let channels: Vec<u8> = parts
+            .iter()
+            .take(3)
+            .filter_map(|s| s.parse::<u8>().ok())
+            .collect();
+
+        if channels.len() < 3 {
+            return Err(format!(
+                "Invalid color '{value}', expected rgba(red, green, blue, alpha)"
+            ));
+        }
+
+        let alpha = match parts.get(3) {
+            Some(alpha) => alpha
+                .parse::<f32>()
+                .ok()
+                .filter(|alpha| !alpha.is_nan())
+                .ok_or_else(|| format!("Invalid alpha value in '{value}', expected a number"))?
+                .clamp(0.0, 1.0),
+            None => 1.0,
         };
 
-        let rgba_value: RGBA = rgba_tuple.try_into().unwrap();
+        let rgba: RGBA = (channels[0], channels[1], channels[2], 1f32)
+            .try_into()
+            .unwrap();
+
+        Ok(RGBAColor { rgba, alpha })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(value: &str) -> RGBAColor {
+        RGBAColor::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn alpha_is_clamped_to_the_valid_range() {
+        assert!((color("rgba(0, 0, 0, 1.5)").alpha - 1.0).abs() < f32::EPSILON);
+        assert!(color("rgba(0, 0, 0, -0.2)").alpha.abs() < f32::EPSILON);
+        assert!((color("rgba(0, 0, 0)").alpha - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn alpha_byte_is_rounded_at_the_boundaries() {
+        assert_eq!(color("rgba(0, 0, 0, 0.0)").alpha_byte(), 0);
+        assert_eq!(color("rgba(0, 0, 0, 0.5)").alpha_byte(), 128);
+        assert_eq!(color("rgba(0, 0, 0, 1.0)").alpha_byte(), 255);
+        assert_eq!(color("rgba(0, 0, 0, 1.5)").alpha_byte(), 255);
+        assert_eq!(color("rgba(0, 0, 0, -0.2)").alpha_byte(), 0);
+        assert_eq!(color("rgba(0, 0, 0, 0.65)").alpha_byte(), 166);
+    }
+
+    #[test]
+    fn hex_only_includes_the_alpha_of_translucent_colors() {
+        assert_eq!(color("rgba(220, 215, 186, 1)").hex(), "#DCD7BA");
+        assert_eq!(color("rgba(220, 215, 186, 0.65)").hex(), "#DCD7BAA6");
+    }
+
+    #[test]
+    fn rgb_follows_changes_to_the_color() {
+        let mut rgba_color = color("rgba(220, 215, 186, 0.65)");
+        assert_eq!(rgba_color.rgb(), (220, 215, 186));
+
+        rgba_color.rgba.set_red(31);
+        assert_eq!(rgba_color.rgb(), (31, 215, 186));
+    }
 
-        Ok(RGBAColor(rgba_value))
+    #[test]
+    fn alpha_must_be_a_number() {
+        assert!(RGBAColor::try_from("rgba(0, 0, 0, NaN)").is_err());
+        assert!(RGBAColor::try_from("rgba(0, 0, 0, half)").is_err());
     }
 }
@@ -0,0 +1,10 @@
+use crate::models::config::Config;
+use crate::utils::paths::Paths;
+
+/// Where the themes and the state of the theme picker are, and the global configuration. Loaded
+/// once at startup and passed to everything that applies or reads themes.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub paths: Paths,
+    pub config: Config,
+}
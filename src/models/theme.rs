@@ -1,11 +1,23 @@
+use crate::models::theme_metadata::ThemeMetadata;
+use crate::utils::images::Images;
 use ratatui::prelude::Line;
+use ratatui::style::Stylize;
 use ratatui::widgets::ListItem;
+use regex::Regex;
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::SystemTime;
 
-#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+/// Matches a markdown link or image, capturing its text.
+static LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!?\[([^\]]*)\]\([^)]*\)").unwrap());
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
 pub enum ColorScheme {
     Light,
+    #[default]
     Dark,
 }
 
@@ -17,9 +29,24 @@ pub struct Theme {
     pub btop_theme_path: Option<PathBuf>,
     pub color_scheme: ColorScheme,
     pub gtk_theme: String,
+    pub variables_file_name: String,
+    pub thumbnail_path: Option<PathBuf>,
+    /// The directory name of the theme whose variables this theme extends.
+    pub extends: Option<String>,
+    /// The red, green, and blue components of the solid color used as the wallpaper when the
+    /// theme has no wallpapers.
+    pub wallpaper_color: Option<(u8, u8, u8)>,
+    /// Words describing the theme, such as `dark` or `warm`, for filtering large collections.
+    pub tags: Vec<String>,
+    /// Whether the theme had a variables file, or extended a theme to get its variables from,
+    /// when it was loaded. See [`Theme::is_complete()`].
+    complete: bool,
 }
 
 impl Theme {
+    /// The name of the file containing the theme's SCSS variables, unless `meta.toml` says otherwise.
+    pub const DEFAULT_VARIABLES_FILE_NAME: &'static str = "theme-variables.scss";
+
     #[must_use]
     pub fn new(
         name: &str,
@@ -28,7 +55,10 @@ impl Theme {
         btop_theme_path: Option<PathBuf>,
         color_scheme: ColorScheme,
         gtk_theme: &str,
+        variables_file_name: &str,
     ) -> Self {
+        let complete = directory_path.join(variables_file_name).is_file();
+
         Self {
             name: name.to_string(),
             description: description.to_string(),
@@ -36,17 +66,307 @@ impl Theme {
             btop_theme_path,
             color_scheme,
             gtk_theme: gtk_theme.to_string(),
+            variables_file_name: variables_file_name.to_string(),
+            thumbnail_path: None,
+            extends: None,
+            wallpaper_color: None,
+            tags: Vec::new(),
+            complete,
+        }
+    }
+
+    /// Loads the theme in the directory from its metadata file. Without a name, the theme is named
+    /// after its directory, and without a description, the first paragraph of its
+    /// `description.md` or `README.md` is used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory has no metadata file, or if it cannot be parsed or
+    /// contains an invalid value.
+    pub fn from_dir(path: &Path) -> Result<Theme, String> {
+        let meta = ThemeMetadata::read(path)?
+            .ok_or_else(|| format!("No metadata file found in {}", path.display()))?;
+
+        let name = meta
+            .name
+            .filter(|name| !name.trim().is_empty())
+            .or_else(|| Some(path.file_name()?.to_string_lossy().to_string()))
+            .unwrap_or_default();
+
+        let description = meta
+            .description
+            .filter(|description| !description.trim().is_empty())
+            .or_else(|| Self::read_markdown_description(path))
+            .unwrap_or_default();
+
+        let wallpaper_color = meta
+            .wallpaper_color
+            .as_deref()
+            .map(ThemeMetadata::parse_wallpaper_color)
+            .transpose()?;
+
+        let theme = Theme::new(
+            name.as_str(),
+            description.as_str(),
+            path.to_path_buf(),
+            meta.btop_theme_path.map(PathBuf::from),
+            meta.color_scheme,
+            meta.gtk_theme.as_str(),
+            meta.variables_file
+                .as_deref()
+                .unwrap_or(Theme::DEFAULT_VARIABLES_FILE_NAME),
+        );
+
+        let thumbnail_path = match meta.thumbnail {
+            Some(thumbnail) => Self::resolve_thumbnail_path(path, &thumbnail)?,
+            None => None,
+        };
+
+        Ok(Theme {
+            thumbnail_path,
+            complete: theme.complete || meta.extends.is_some(),
+            extends: meta.extends,
+            wallpaper_color,
+            tags: meta.tags,
+            ..theme
+        })
+    }
+
+    /// Resolves the thumbnail, relative to the theme directory, with its symbolic links resolved,
+    /// so that it cannot be read from anywhere else. A missing thumbnail is not shown.
+    fn resolve_thumbnail_path(
+        theme_path: &Path,
+        thumbnail: &str,
+    ) -> Result<Option<PathBuf>, String> {
+        if Path::new(thumbnail).is_absolute() {
+            return Err(format!(
+                "The thumbnail {thumbnail} must be relative to the theme directory"
+            ));
+        }
+
+        let Ok(resolved_path) = fs::canonicalize(theme_path.join(thumbnail)) else {
+            return Ok(None);
+        };
+        let theme_path = fs::canonicalize(theme_path)
+            .map_err(|e| format!("Could not resolve {}: {e}", theme_path.display()))?;
+
+        if !resolved_path.starts_with(&theme_path) {
+            return Err(format!(
+                "The thumbnail {} is outside of the theme directory {}",
+                resolved_path.display(),
+                theme_path.display()
+            ));
+        }
+
+        Ok(Some(resolved_path))
+    }
+
+    /// Reads the first paragraph of the theme's `description.md` or `README.md`, stripped of
+    /// markdown syntax. Used when the metadata does not contain a description.
+    fn read_markdown_description(theme_path: &Path) -> Option<String> {
+        let contents = ["description.md", "README.md"]
+            .iter()
+            .find_map(|file_name| fs::read_to_string(theme_path.join(file_name)).ok())?;
+
+        let paragraph = contents
+            .lines()
+            .map(str::trim)
+            .skip_while(|line| line.is_empty() || line.starts_with('#'))
+            .take_while(|line| !line.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+
+        let description = LINK_REGEX
+            .replace_all(&paragraph, "$1")
+            .chars()
+            .filter(|c| !matches!(c, '*' | '_' | '`'))
+            .collect::<String>();
+
+        if description.is_empty() {
+            return None;
+        }
+
+        Some(description)
+    }
+
+    /// Checks whether the theme has all of the tags, or if `any`, at least one of them, ignoring
+    /// case. Every theme matches no tags, while a theme without tags never matches any.
+    #[must_use]
+    pub fn matches_tags(&self, tags: &[String], any: bool) -> bool {
+        if tags.is_empty() {
+            return true;
+        }
+
+        let has_tag = |tag: &String| {
+            self.tags
+                .iter()
+                .any(|theme_tag| theme_tag.eq_ignore_ascii_case(tag))
+        };
+
+        if any {
+            tags.iter().any(has_tag)
+        } else {
+            tags.iter().all(has_tag)
         }
     }
 
+    /// Gets the name of the theme's directory, which identifies the theme on disk.
+    #[must_use]
+    pub fn dir_name(&self) -> String {
+        self.directory_path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+    }
+
     #[must_use]
     pub fn get_theme_variables_css_file_path(&self) -> PathBuf {
-        self.directory_path.join("theme-variables.scss")
+        self.directory_path.join(&self.variables_file_name)
+    }
+
+    /// Checks whether the theme has a variables file, or extends a theme to get its variables
+    /// from. An incomplete theme cannot be applied. This is checked when the theme is loaded, so
+    /// that listing the themes does not touch the disk.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Gets the path to the theme's `wallpapers/` directory.
+    #[must_use]
+    pub fn wallpapers_path(&self) -> PathBuf {
+        self.directory_path.join("wallpapers")
+    }
+
+    /// Counts the wallpapers in the theme's `wallpapers/` directory, which are the images, and the
+    /// videos if `include_videos`, as when videos are enabled in the configuration. The count is 0
+    /// if the directory does not exist.
+    #[must_use]
+    pub fn wallpaper_count(&self, include_videos: bool) -> usize {
+        let wallpapers_path = self.wallpapers_path();
+        let video_count = if include_videos {
+            Images::list_videos(&wallpapers_path).map_or(0, |videos| videos.len())
+        } else {
+            0
+        };
+
+        Images::list(&wallpapers_path).map_or(0, |images| images.len()) + video_count
+    }
+
+    /// Gets the last time a file directly inside the theme's directory was modified, or `None` if
+    /// the directory cannot be read.
+    #[must_use]
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        fs::read_dir(&self.directory_path)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+            .max()
     }
 }
 
 impl From<&Theme> for ListItem<'_> {
     fn from(value: &Theme) -> Self {
+        if !value.is_complete() {
+            return ListItem::new(Line::from(vec![
+                value.name.clone().dark_gray(),
+                " ⚠ incomplete".yellow(),
+            ]));
+        }
+
         ListItem::new(Line::from(value.name.clone()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_theme(directory: &Path, meta: &str) -> Theme {
+        fs::write(
+            directory.join("meta.toml"),
+            format!("color_scheme = \"Dark\"\ngtk_theme = \"Adwaita\"\n{meta}"),
+        )
+        .unwrap();
+
+        Theme::from_dir(directory).unwrap()
+    }
+
+    #[test]
+    fn from_dir_marks_themes_without_variables_as_incomplete() {
+        let directory = TempDir::new().unwrap();
+
+        assert!(!write_theme(directory.path(), "").is_complete());
+    }
+
+    #[test]
+    fn from_dir_marks_themes_with_variables_as_complete() {
+        let directory = TempDir::new().unwrap();
+        fs::write(
+            directory.path().join(Theme::DEFAULT_VARIABLES_FILE_NAME),
+            "$bg: #000;\n",
+        )
+        .unwrap();
+
+        assert!(write_theme(directory.path(), "").is_complete());
+    }
+
+    #[test]
+    fn from_dir_marks_extending_themes_as_complete() {
+        let directory = TempDir::new().unwrap();
+
+        assert!(write_theme(directory.path(), "extends = \"kanagawa\"\n").is_complete());
+    }
+
+    #[test]
+    fn from_dir_loads_a_theme_with_only_a_name() {
+        let directory = TempDir::new().unwrap();
+        fs::write(directory.path().join("meta.toml"), "name = \"Kanagawa\"\n").unwrap();
+
+        let theme = Theme::from_dir(directory.path()).unwrap();
+
+        assert_eq!(theme.name, "Kanagawa");
+        assert_eq!(theme.color_scheme, ColorScheme::Dark);
+        assert_eq!(theme.gtk_theme, "");
+    }
+
+    #[test]
+    fn from_dir_resolves_the_thumbnail_in_the_theme_directory() {
+        let directory = TempDir::new().unwrap();
+        fs::write(directory.path().join("thumbnail.png"), "").unwrap();
+
+        assert_eq!(
+            write_theme(directory.path(), "thumbnail = \"thumbnail.png\"\n").thumbnail_path,
+            Some(fs::canonicalize(directory.path().join("thumbnail.png")).unwrap())
+        );
+    }
+
+    #[test]
+    fn from_dir_rejects_thumbnails_outside_of_the_theme_directory() {
+        let themes_dir = TempDir::new().unwrap();
+        let theme_path = themes_dir.path().join("kanagawa");
+        fs::create_dir(&theme_path).unwrap();
+        fs::write(themes_dir.path().join("secret.png"), "").unwrap();
+
+        for thumbnail in ["../secret.png", "/etc/passwd"] {
+            fs::write(
+                theme_path.join("meta.toml"),
+                format!("thumbnail = \"{thumbnail}\"\n"),
+            )
+            .unwrap();
+
+            assert!(Theme::from_dir(&theme_path).is_err(), "{thumbnail}");
+        }
+    }
+
+    #[test]
+    fn is_complete_does_not_touch_the_disk_after_loading() {
+        let directory = TempDir::new().unwrap();
+        let variables_path = directory.path().join(Theme::DEFAULT_VARIABLES_FILE_NAME);
+        fs::write(&variables_path, "$bg: #000;\n").unwrap();
+        let theme = write_theme(directory.path(), "");
+
+        fs::remove_file(variables_path).unwrap();
+
+        assert!(theme.is_complete());
+    }
+}
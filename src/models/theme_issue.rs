@@ -0,0 +1,47 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// A problem with a theme that can be detected before applying it.
+#[derive(Debug, Clone)]
+pub enum ThemeIssue {
+    /// The theme has no variables file, and does not extend another theme. It cannot be applied.
+    MissingVariablesFile(PathBuf),
+    /// The theme's variables could not be read.
+    InvalidVariables(String),
+    /// The theme's `wallpapers/` directory contains no wallpapers.
+    NoWallpapers(PathBuf),
+    /// The theme's wallpapers could not be listed, e.g. because `wallpapers/` is a symbolic link
+    /// out of the theme directory.
+    InvalidWallpapers(String),
+    /// The theme's thumbnail is not a PNG file, the only format the theme picker can show.
+    UnsupportedThumbnail(PathBuf),
+    /// Placeholders in the Kitty template that the theme has no variables for.
+    UnresolvedPlaceholders(Vec<String>),
+}
+
+impl Display for ThemeIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThemeIssue::MissingVariablesFile(path) => {
+                write!(f, "The variables file {} does not exist", path.display())
+            }
+            ThemeIssue::InvalidVariables(error) => write!(f, "{error}"),
+            ThemeIssue::NoWallpapers(path) => {
+                write!(f, "There are no wallpapers in {}", path.display())
+            }
+            ThemeIssue::InvalidWallpapers(error) => {
+                write!(f, "The wallpapers cannot be listed: {error}")
+            }
+            ThemeIssue::UnsupportedThumbnail(path) => write!(
+                f,
+                "The thumbnail {} is not a PNG file and cannot be shown",
+                path.display()
+            ),
+            ThemeIssue::UnresolvedPlaceholders(placeholders) => write!(
+                f,
+                "The Kitty template uses variables the theme does not define: {}",
+                placeholders.join(", ")
+            ),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use crate::models::hex_color::HexColor;
+use crate::models::theme::ColorScheme;
+use crate::models::wallpaper_strategy::WallpaperStrategy;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// The contents of a theme's metadata file, as written by the theme's author.
+#[derive(Debug, Deserialize)]
+pub struct ThemeMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub btop_theme_path: Option<String>,
+    #[serde(default)]
+    pub color_scheme: ColorScheme,
+    #[serde(default)]
+    pub gtk_theme: String,
+    pub variables_file: Option<String>,
+    pub thumbnail: Option<String>,
+    pub extends: Option<String>,
+    #[serde(default)]
+    pub wallpaper_strategy: WallpaperStrategy,
+    pub wallpaper_color: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl ThemeMetadata {
+    /// The names of the theme metadata files, in the order they are looked for.
+    pub const FILE_NAMES: &'static [&'static str] =
+        &["meta.toml", "meta.yaml", "meta.yml", "meta.json"];
+
+    /// Checks whether the theme's directory contains one of the [`Self::FILE_NAMES`].
+    #[must_use]
+    pub fn exists(theme_path: &Path) -> bool {
+        Self::FILE_NAMES
+            .iter()
+            .any(|file_name| theme_path.join(file_name).is_file())
+    }
+
+    /// Reads the first metadata file found in the theme's directory, looking for `meta.toml`,
+    /// then `meta.yaml` or `meta.yml`, then `meta.json`. Returns `None` if there is no metadata
+    /// file.
+    ///
+    /// # Errors
+    ///
+    /// The metadata file cannot be parsed, or is missing a required field.
+    pub fn read(theme_path: &Path) -> Result<Option<Self>, String> {
+        let Some((file_name, contents)) = Self::read_file(theme_path) else {
+            return Ok(None);
+        };
+
+        let meta = match file_name {
+            "meta.toml" => toml::from_str(&contents).map_err(|e| e.to_string()),
+            "meta.json" => serde_json::from_str(&contents).map_err(|e| e.to_string()),
+            _ => serde_yaml_ng::from_str(&contents).map_err(|e| e.to_string()),
+        };
+
+        meta.map(Some)
+            .map_err(|e| format!("Invalid {file_name}: {}", e.trim_end()))
+    }
+
+    /// Reads the first metadata file found in the theme's directory, returning its name and
+    /// contents.
+    #[must_use]
+    pub fn read_file(theme_path: &Path) -> Option<(&'static str, String)> {
+        Self::FILE_NAMES.iter().find_map(|file_name| {
+            Some((
+                *file_name,
+                fs::read_to_string(theme_path.join(file_name)).ok()?,
+            ))
+        })
+    }
+
+    /// Parses the `wallpaper_color` of a theme's metadata, which can be any color the variables
+    /// can, e.g. `#1f1f28` or `rgba(31, 31, 40, 1)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value is not a color.
+    pub fn parse_wallpaper_color(value: &str) -> Result<(u8, u8, u8), String> {
+        HexColor::try_from(value)
+            .ok()
+            .and_then(|color| color.rgb())
+            .ok_or_else(|| format!("Invalid wallpaper_color '{value}', expected a color"))
+    }
+}
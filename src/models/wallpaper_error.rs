@@ -0,0 +1,54 @@
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Error returned when the wallpaper could not be changed.
+#[derive(Debug, Clone)]
+pub enum WallpaperError {
+    /// The wallpaper could not be selected or set.
+    Failed(String),
+    /// Setting the wallpaper did not finish before the deadline.
+    TimedOut(Duration),
+    /// The program setting the wallpaper could not be executed, e.g. because it is not installed.
+    ProgramMissing(String),
+    /// hyprpaper was still not ready to set the wallpaper after retrying, e.g. because it is not
+    /// running.
+    NotReady(String),
+    /// hyprpaper refused the wallpaper, e.g. because its path is wrong. This is not retried.
+    Rejected(String),
+    /// hyprctl failed without saying why, e.g. because hyprpaper crashed. This is not retried.
+    UnexpectedResponse(String),
+}
+
+impl Display for WallpaperError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WallpaperError::Failed(error) => write!(f, "{error}"),
+            WallpaperError::TimedOut(timeout) => write!(
+                f,
+                "Setting the wallpaper timed out after {} seconds",
+                timeout.as_secs()
+            ),
+            WallpaperError::ProgramMissing(error) => {
+                write!(
+                    f,
+                    "Could not run the program setting the wallpaper: {error}"
+                )
+            }
+            WallpaperError::NotReady(error) => {
+                write!(f, "hyprpaper is not ready, is it running? {error}")
+            }
+            WallpaperError::Rejected(error) => {
+                write!(f, "hyprpaper refused the wallpaper: {error}")
+            }
+            WallpaperError::UnexpectedResponse(error) => {
+                write!(f, "Failed to set wallpaper: {error}")
+            }
+        }
+    }
+}
+
+impl From<String> for WallpaperError {
+    fn from(error: String) -> Self {
+        WallpaperError::Failed(error)
+    }
+}
@@ -0,0 +1,17 @@
+use serde::Deserialize;
+
+/// How the wallpaper of a theme is selected, set with `wallpaper_strategy` in the theme's
+/// metadata, e.g. `wallpaper_strategy = "sequential"` or `wallpaper_strategy = { fixed = "a.png" }`.
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WallpaperStrategy {
+    /// A random wallpaper, avoiding the previous one when rotating.
+    #[default]
+    Random,
+    /// The wallpaper with the given file name.
+    Fixed(String),
+    /// The first wallpaper, sorted by file name.
+    First,
+    /// The wallpaper after the previous one, sorted by file name, starting over after the last.
+    Sequential,
+}
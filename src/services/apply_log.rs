@@ -0,0 +1,109 @@
+use crate::models::apply_error::ApplyError;
+use crate::models::theme::Theme;
+use crate::utils::command_runner::ExecutedCommand;
+use crate::utils::paths::Paths;
+use serde::{Serialize, Serializer};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The result of a single step of applying a theme, such as applying it to one application.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepResult {
+    pub step: String,
+    pub error: Option<String>,
+    /// The wall-clock time the step took, logged in milliseconds.
+    #[serde(rename = "duration_ms", serialize_with = "serialize_millis")]
+    pub duration: Duration,
+}
+
+fn serialize_millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_millis())
+}
+
+#[derive(Serialize)]
+struct ApplyLogEntry<'a> {
+    /// Seconds since the Unix epoch
+    timestamp: u64,
+    theme: &'a str,
+    result: &'static str,
+    error: Option<String>,
+    steps: &'a [StepResult],
+    commands: &'a [ExecutedCommand],
+}
+
+/// Append-only log of theme applications, written as one JSON object per line to
+/// `apply.log` in [`Paths::state_path()`] when [`Self::ENABLED_ENV`] is `1`.
+pub struct ApplyLog;
+
+impl ApplyLog {
+    /// Environment variable that enables the apply log when set to `1`.
+    pub const ENABLED_ENV: &'static str = "NORLYK_LOG";
+
+    /// Checks whether the apply log is enabled by the environment.
+    #[must_use]
+    pub fn is_enabled() -> bool {
+        env::var(Self::ENABLED_ENV).is_ok_and(|value| value == "1")
+    }
+
+    /// Appends an entry describing an application of the theme. Failing to write the log is
+    /// reported on stderr, but never fails the application itself.
+    pub fn write(
+        paths: &Paths,
+        theme: &Theme,
+        steps: &[StepResult],
+        result: &Result<(), ApplyError>,
+        commands: &[ExecutedCommand],
+    ) {
+        if let Err(e) = Self::append(paths, theme, steps, result, commands) {
+            eprintln!("Could not write the apply log: {e}");
+        }
+    }
+
+    /// Gets the path to the apply log.
+    #[must_use]
+    pub fn path(paths: &Paths) -> PathBuf {
+        paths.state_path().join("apply.log")
+    }
+
+    fn append(
+        paths: &Paths,
+        theme: &Theme,
+        steps: &[StepResult],
+        result: &Result<(), ApplyError>,
+        commands: &[ExecutedCommand],
+    ) -> Result<(), String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_secs();
+
+        let (result, error) = match result {
+            Ok(()) => ("ok", None),
+            Err(e @ ApplyError::Partial(_)) => ("partial", Some(e.to_string())),
+            Err(e @ ApplyError::Failed(_)) => ("failed", Some(e.to_string())),
+        };
+
+        let entry = ApplyLogEntry {
+            timestamp,
+            theme: &theme.name,
+            result,
+            error,
+            steps,
+            commands,
+        };
+
+        let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        let path = Self::path(paths);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Could not open {}: {e}", path.display()))?;
+
+        writeln!(file, "{line}").map_err(|e| format!("Could not write to {}: {e}", path.display()))
+    }
+}
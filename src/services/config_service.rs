@@ -0,0 +1,132 @@
+use crate::models::config::Config;
+use crate::models::settings::Settings;
+use crate::utils::paths::Paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct ConfigService;
+
+impl ConfigService {
+    /// Loads the settings: the global configuration from `config_file_path`, or
+    /// [`Paths::global_config_file()`] if it is not given, the themes from `theme_dir`, or
+    /// [`Paths::default_config_path()`], and the state directory from [`Self::state_path()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable `HOME` is not set, the configuration cannot be
+    /// loaded, the state path cannot be expanded, or the state directory cannot be created.
+    pub fn settings(
+        theme_dir: Option<PathBuf>,
+        config_file_path: Option<&Path>,
+    ) -> Result<Settings, String> {
+        let config = Self::load(config_file_path)?;
+        let config_path = match theme_dir {
+            Some(theme_dir) => theme_dir,
+            None => Paths::default_config_path()?,
+        };
+
+        let state_path = Self::state_path(&config, &config_path)?;
+
+        Ok(Settings {
+            paths: Paths::new(config_path, state_path),
+            config,
+        })
+    }
+
+    /// Gets the state directory configured in `paths.state`, creating it if it does not exist, or
+    /// the theme directory `config_path` if none is configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured path cannot be expanded, or the directory cannot be
+    /// created.
+    pub fn state_path(config: &Config, config_path: &Path) -> Result<PathBuf, String> {
+        let Some(state_path) = &config.paths.state else {
+            return Ok(config_path.to_path_buf());
+        };
+
+        let state_path = Paths::expand(state_path)?;
+
+        fs::create_dir_all(&state_path)
+            .map_err(|e| format!("Could not create {}: {e}", state_path.display()))?;
+
+        Ok(state_path)
+    }
+
+    /// Loads the global configuration from `config_file_path`, or [`Paths::global_config_file()`]
+    /// if it is not given. A missing file results in the default configuration, unless the file
+    /// was given explicitly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable `HOME` is not set, if the file exists but
+    /// cannot be read or parsed, or if an explicitly given file does not exist.
+    pub fn load(config_file_path: Option<&Path>) -> Result<Config, String> {
+        let config_file_path = match config_file_path {
+            Some(path) if !path.exists() => {
+                return Err(format!("{} does not exist", path.display()));
+            }
+            Some(path) => path.to_path_buf(),
+            None => Paths::global_config_file()?,
+        };
+
+        if !config_file_path.exists() {
+            return Ok(Config::default());
+        }
+
+        let contents = fs::read_to_string(&config_file_path)
+            .map_err(|e| format!("Could not read {}: {e}", config_file_path.display()))?;
+
+        toml::from_str(&contents)
+            .map_err(|e| format!("Could not parse {}: {e}", config_file_path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn settings_carry_the_loaded_config_and_its_state_directory() {
+        let directory = TempDir::new().unwrap();
+        let state_path = directory.path().join("state");
+        let config_file_path = directory.path().join("config.toml");
+        fs::write(
+            &config_file_path,
+            format!(
+                "[paths]\nstate = \"{}\"\n\n[hypr]\nlive = true\n",
+                state_path.display()
+            ),
+        )
+        .unwrap();
+
+        let settings = ConfigService::settings(
+            Some(directory.path().to_path_buf()),
+            Some(&config_file_path),
+        )
+        .unwrap();
+
+        assert!(settings.config.hypr.live);
+        assert_eq!(settings.paths.config_path(), directory.path());
+        assert_eq!(settings.paths.state_path(), state_path);
+        assert!(state_path.is_dir());
+    }
+
+    #[test]
+    fn state_path_defaults_to_the_theme_directory() {
+        let directory = TempDir::new().unwrap();
+
+        assert_eq!(
+            ConfigService::state_path(&Config::default(), directory.path()),
+            Ok(directory.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn load_fails_for_a_missing_explicit_file() {
+        let directory = TempDir::new().unwrap();
+
+        assert!(ConfigService::load(Some(&directory.path().join("config.toml"))).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use crate::models::settings::Settings;
+use crate::services::theme_service::ThemeService;
+use crate::services::themers::waybar::WaybarThemer;
+use crate::utils::command_runner::CommandRunner;
+use crate::utils::paths::Paths;
+use std::fs;
+
+/// The outcome of checking a single requirement of the theme picker.
+pub struct Diagnosis {
+    pub description: String,
+    pub result: Result<(), String>,
+    /// How to fix the problem if the check failed.
+    pub hint: &'static str,
+    /// Whether the theme picker cannot work at all if the check failed.
+    pub critical: bool,
+}
+
+pub struct DoctorService;
+
+impl DoctorService {
+    /// The external programs used when applying a theme, whether each is critical, and how to fix
+    /// it being missing.
+    const PROGRAMS: [(&'static str, bool, &'static str); 4] = [
+        ("hyprctl", true, "Install Hyprland, which provides hyprctl"),
+        (
+            "waybar",
+            false,
+            "Install Waybar, or ignore this if you don't use it",
+        ),
+        (
+            "kitty",
+            false,
+            "Install Kitty, or ignore this if you don't use it",
+        ),
+        (
+            "gsettings",
+            false,
+            "Install GLib, which provides gsettings for GTK",
+        ),
+    ];
+
+    /// Checks the environment for everything the theme picker needs in order to apply themes.
+    #[must_use]
+    pub fn diagnose(settings: &Settings) -> Vec<Diagnosis> {
+        let mut diagnoses = vec![
+            Diagnosis {
+                description: "The HOME environment variable is set".to_string(),
+                result: Paths::user_home().map(|_| ()),
+                hint: "Set HOME to your home directory",
+                critical: true,
+            },
+            Diagnosis {
+                description: "The theme directory is readable".to_string(),
+                result: Self::check_config_dir(settings),
+                hint: "Create ~/.local/share/norlyk-themes/ or pass --theme-dir",
+                critical: true,
+            },
+            Diagnosis {
+                description: "At least one theme is installed".to_string(),
+                result: Self::check_themes(settings),
+                hint: "Add a directory containing a meta.toml to the theme directory",
+                critical: true,
+            },
+            Diagnosis {
+                description: "The current theme symlink is valid".to_string(),
+                result: Self::check_current_theme(settings),
+                hint: "Apply a theme with `norlyk theme <name>`",
+                critical: false,
+            },
+        ];
+
+        // The sass program is configurable, so the configured one is looked for
+        let (description, result) = match WaybarThemer::sass_command(&settings.config) {
+            Ok(sass_command) => {
+                let sass_program = sass_command.get_program().to_string_lossy();
+
+                (
+                    format!("{sass_program} is installed"),
+                    CommandRunner::find_executable(&sass_program)
+                        .map(|_| ())
+                        .ok_or_else(|| format!("{sass_program} was not found in PATH")),
+                )
+            }
+            Err(e) => ("The sass command is valid".to_string(), Err(e)),
+        };
+
+        diagnoses.push(Diagnosis {
+            description,
+            result,
+            hint: "Install Dart Sass, or set the sass command compiling the Waybar stylesheet in config.toml",
+            critical: true,
+        });
+
+        for (program, critical, hint) in Self::PROGRAMS {
+            diagnoses.push(Diagnosis {
+                description: format!("{program} is installed"),
+                result: CommandRunner::find_executable(program)
+                    .map(|_| ())
+                    .ok_or_else(|| format!("{program} was not found in PATH")),
+                hint,
+                critical,
+            });
+        }
+
+        diagnoses
+    }
+
+    fn check_config_dir(settings: &Settings) -> Result<(), String> {
+        let config_path = settings.paths.config_path();
+
+        fs::read_dir(config_path)
+            .map(|_| ())
+            .map_err(|e| format!("Could not read {}: {e}", config_path.display()))
+    }
+
+    fn check_themes(settings: &Settings) -> Result<(), String> {
+        if ThemeService::get_available_themes(settings)?.is_empty() {
+            return Err("No themes were found".to_string());
+        }
+
+        Ok(())
+    }
+
+    fn check_current_theme(settings: &Settings) -> Result<(), String> {
+        let current_theme_path = settings.paths.current_theme();
+
+        // Follows the symlink, so a broken symlink is reported as well
+        fs::metadata(&current_theme_path)
+            .map(|_| ())
+            .map_err(|e| format!("Could not resolve {}: {e}", current_theme_path.display()))
+    }
+}
@@ -0,0 +1,100 @@
+use crate::models::hex_color::HexColor;
+use crate::services::themers::ThemeContext;
+use crate::utils::command_runner::CommandRunner;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A user-provided executable run while applying a theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Run before anything is applied. If it fails, the theme is not applied.
+    PreApply,
+    /// Run after every step of applying the theme succeeded.
+    PostApply,
+}
+
+impl Hook {
+    #[must_use]
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Hook::PreApply => "pre-apply",
+            Hook::PostApply => "post-apply",
+        }
+    }
+}
+
+pub struct HookService;
+
+impl HookService {
+    /// Runs the hook from the global `hooks/` directory in the config directory, followed by the
+    /// hook in the theme's own directory. Hooks that don't exist are skipped.
+    ///
+    /// Each hook receives the theme's name and directory as arguments, and every theme variable as
+    /// an environment variable, e.g. `$backgroundColor` as `NORLYK_VAR_BACKGROUND_COLOR`. Colors
+    /// are converted to hex.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a hook cannot be executed or exits unsuccessfully.
+    pub fn run(hook: Hook, context: &ThemeContext<'_>) -> Result<(), String> {
+        for hook_path in Self::hook_paths(hook, context) {
+            if !hook_path.is_file() {
+                continue;
+            }
+
+            let mut command = Command::new(&hook_path);
+            command
+                .arg(&context.theme.name)
+                .arg(&context.theme.directory_path);
+
+            for (name, value) in &context.variables {
+                let value: String = match HexColor::try_from(value) {
+                    Ok(hex_color) => hex_color.into(),
+                    Err(_) => value.clone(),
+                };
+
+                command.env(Self::env_name(name), value);
+            }
+
+            CommandRunner::run(&mut command)
+                .map_err(|e| format!("The {} hook failed: {e}", hook.file_name()))?;
+        }
+
+        Ok(())
+    }
+
+    fn hook_paths(hook: Hook, context: &ThemeContext<'_>) -> Vec<PathBuf> {
+        vec![
+            context
+                .settings
+                .paths
+                .config_path()
+                .join("hooks")
+                .join(hook.file_name()),
+            context.theme.directory_path.join(hook.file_name()),
+        ]
+    }
+
+    /// Converts a variable name such as `backgroundColor` or `border-color` to an environment
+    /// variable name such as `NORLYK_VAR_BACKGROUND_COLOR` or `NORLYK_VAR_BORDER_COLOR`.
+    fn env_name(variable_name: &str) -> String {
+        let mut env_name = String::from("NORLYK_VAR_");
+        let mut previous_is_lowercase = false;
+
+        for c in variable_name.chars() {
+            if c.is_ascii_uppercase() && previous_is_lowercase {
+                env_name.push('_');
+            }
+
+            if c.is_ascii_alphanumeric() {
+                env_name.push(c.to_ascii_uppercase());
+            } else {
+                env_name.push('_');
+            }
+
+            previous_is_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+        }
+
+        env_name
+    }
+}
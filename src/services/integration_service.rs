@@ -0,0 +1,115 @@
+use crate::models::apply_options::ApplyOptions;
+use crate::models::component::Component;
+use crate::models::config::Config;
+use crate::models::settings::Settings;
+use crate::services::theme_service::ThemeService;
+use crate::services::themers::hypr::HyprThemer;
+use crate::services::themers::kitty::KittyThemer;
+use crate::services::themers::waybar::WaybarThemer;
+use crate::services::themers::wezterm::WeztermThemer;
+use crate::utils::command_runner::CommandRunner;
+use crate::utils::paths::Paths;
+use std::path::Path;
+
+/// Whether a theme would be applied to a component on this system.
+pub struct Integration {
+    pub component: Component,
+    /// Why the component would be skipped, or `None` if the theme would be applied to it.
+    pub skip_reason: Option<String>,
+}
+
+pub struct IntegrationService;
+
+impl IntegrationService {
+    /// Checks which components a theme would be applied to with `options`, based on the
+    /// configuration files and programs found on this system.
+    #[must_use]
+    pub fn discover(settings: &Settings, options: &ApplyOptions) -> Vec<Integration> {
+        let reloads_applications = ThemeService::reloads_applications(options);
+
+        Component::ALL
+            .into_iter()
+            .map(|component| {
+                let result = if options.is_enabled(component) {
+                    Self::check(&settings.config, component, reloads_applications)
+                } else {
+                    Err("Turned off".to_string())
+                };
+
+                Integration {
+                    component,
+                    skip_reason: result.err(),
+                }
+            })
+            .collect()
+    }
+
+    fn check(
+        config: &Config,
+        component: Component,
+        reloads_applications: bool,
+    ) -> Result<(), String> {
+        match component {
+            Component::Hyprland => {
+                Self::require_parent_dir(&HyprThemer::style_variables_file_path(config)?)
+            }
+            Component::Kitty => Self::require_file(&KittyThemer::template_file_path(config)?),
+            Component::Wezterm => Self::require_dir(&WeztermThemer::config_dir_path()?),
+            Component::Waybar => {
+                Self::require_parent_dir(&WaybarThemer::style_file_path(config)?)?;
+
+                let sass_command = WaybarThemer::sass_command(config)?;
+
+                Self::require_program(&sass_command.get_program().to_string_lossy())
+            }
+            Component::Btop => Self::require_program("btop"),
+            Component::Gtk => {
+                // The GTK settings only exist in the running session
+                Self::require_reload(reloads_applications)?;
+                Self::require_program("gsettings")
+            }
+            Component::Neovim => Self::require_dir(&Paths::user_home()?.join(".config/nvim")),
+            Component::Wallpaper => {
+                Self::require_reload(reloads_applications)?;
+                Self::require_program("hyprctl")
+            }
+        }
+    }
+
+    fn require_reload(reloads_applications: bool) -> Result<(), String> {
+        if reloads_applications {
+            Ok(())
+        } else {
+            Err("Applications are not reloaded".to_string())
+        }
+    }
+
+    fn require_program(program: &str) -> Result<(), String> {
+        CommandRunner::find_executable(program)
+            .map(|_| ())
+            .ok_or_else(|| format!("{program} was not found in PATH"))
+    }
+
+    fn require_file(path: &Path) -> Result<(), String> {
+        if path.is_file() {
+            Ok(())
+        } else {
+            Err(format!("{} does not exist", path.display()))
+        }
+    }
+
+    fn require_dir(path: &Path) -> Result<(), String> {
+        if path.is_dir() {
+            Ok(())
+        } else {
+            Err(format!("{} does not exist", path.display()))
+        }
+    }
+
+    fn require_parent_dir(path: &Path) -> Result<(), String> {
+        match path.parent() {
+            Some(parent) => Self::require_dir(parent),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,2 +1,8 @@
+pub mod apply_log;
+pub mod config_service;
+pub mod doctor_service;
+pub mod hook_service;
+pub mod integration_service;
 pub mod theme_service;
 pub mod themers;
+pub mod verification_service;
@@ -1,73 +1,541 @@
-use crate::models::theme::{ColorScheme, Theme};
+use crate::models::apply_error::ApplyError;
+use crate::models::apply_options::ApplyOptions;
+use crate::models::component::Component;
+use crate::models::hex_color::HexColor;
+use crate::models::loaded_themes::{LoadedThemes, SkippedTheme};
+use crate::models::settings::Settings;
+use crate::models::theme::Theme;
+use crate::models::theme_issue::ThemeIssue;
+use crate::models::theme_metadata::ThemeMetadata;
+use crate::models::wallpaper_error::WallpaperError;
+use crate::models::wallpaper_strategy::WallpaperStrategy;
+use crate::services::apply_log::{ApplyLog, StepResult};
+use crate::services::hook_service::{Hook, HookService};
 use crate::services::themers::btop::BtopThemer;
 use crate::services::themers::gtk::GtkThemer;
 use crate::services::themers::hypr::HyprThemer;
 use crate::services::themers::kitty::KittyThemer;
 use crate::services::themers::nvim::NvimThemer;
 use crate::services::themers::waybar::WaybarThemer;
+use crate::services::themers::wezterm::WeztermThemer;
 use crate::services::themers::{ThemeContext, Themer};
+use crate::utils::command_runner::CommandRunner;
+use crate::utils::directory::Directory;
+use crate::utils::file_system::{SystemFs, VerboseFs};
+use crate::utils::images::Images;
 use crate::utils::paths::Paths;
 use crate::utils::symlink::Symlink;
+use crate::utils::template::Template;
+use crate::utils::terminal_graphics::TerminalGraphics;
 use rand::prelude::IndexedRandom;
 use regex::Regex;
-use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
 use std::fs;
-use std::io::Error;
+use std::io::{Error, ErrorKind};
+use std::num::NonZeroUsize;
+use std::panic;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{self, Command, Output, Stdio};
+use std::sync::LazyLock;
 use std::thread;
-use std::time::Duration;
-
-#[derive(Deserialize)]
-struct RawThemeMetadata {
-    name: String,
-    description: String,
-    btop_theme_path: Option<String>,
-    color_scheme: ColorScheme,
-    gtk_theme: String,
-}
+use std::time::{Duration, Instant};
 
 pub struct ThemeService;
 
+// The patterns are compiled once, since they are matched against every variable of every theme
+
+/// Matches a `:root { ... }` rule of a CSS file, capturing its declarations.
+static ROOT_RULE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r":root\s*\{([^}]*)\}").unwrap());
+
+/// Matches a CSS value that is only a `var(--name)` reference, capturing the name.
+static CSS_VAR_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^var\(--([\w-]+)\)$").unwrap());
+
+/// Matches a `@use "file" as namespace` statement, capturing the file and the optional namespace.
+static USE_IMPORT_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^@use\s+"([^"]*)"(?:\s+as\s+(\*|[\w-]+))?$"#).unwrap());
+
+/// Matches a `map.get($map, key)` or `map-get(colors.$map, key)` call, capturing the namespace,
+/// the map and the key.
+static MAP_GET_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^map[.-]get\((?:([\w-]+)\.)?\$([\w-]+),(.+)\)$").unwrap());
+
+/// Matches a `contrast_color(...)` call, capturing its arguments.
+static CONTRAST_COLOR_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^contrast[_-]color\((.+)\)$").unwrap());
+
+/// Matches a `colors.$bg` reference anywhere in a value, capturing the namespace and the name.
+static NAMESPACED_REFERENCE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([\w-]+)\.\$([\w-]+)").unwrap());
+
+/// Matches the name of a function call, such as `linear-gradient(` or `color.adjust(`.
+static FUNCTION_CALL_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"([A-Za-z_][\w.-]*)\(").unwrap());
+
+/// Matches a `#{...}` interpolation, capturing its expression.
+static INTERPOLATION_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"#\{([^}]*)\}").unwrap());
+
+/// Matches a value that is only a `$bg` or `colors.$bg` reference, capturing the namespace and
+/// the name.
+static VARIABLE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:([\w-]+)\.)?\$([\w-]+)$").unwrap());
+
+/// Records the steps of applying a theme, passing each one to the caller's progress callback.
+struct StepRecorder<'a> {
+    steps: Vec<StepResult>,
+    on_step: &'a mut dyn FnMut(&StepResult),
+}
+
+impl StepRecorder<'_> {
+    /// Runs a step of applying a theme, recording its result and how long it took.
+    fn run(&mut self, step: &str, run: impl FnOnce() -> Result<(), String>) -> Result<(), String> {
+        let start = Instant::now();
+        let result = run();
+
+        let step_result = StepResult {
+            step: step.to_string(),
+            error: result.clone().err(),
+            duration: start.elapsed(),
+        };
+        (self.on_step)(&step_result);
+        self.steps.push(step_result);
+
+        result
+    }
+}
+
 impl ThemeService {
-    /// Sets the theme by configuring Hypr, Waybar, and wallpaper settings.
+    /// Name of the file recording the source directory of a theme copied into `current`.
+    pub const COPIED_FROM_FILE_NAME: &'static str = ".norlyk-copied-from";
+
+    /// Global Sass functions that are not part of CSS, whose result cannot be known without
+    /// evaluating the SCSS. Functions of Sass modules, such as `color.adjust()`, are recognized by
+    /// their namespace instead. Every other function, such as `linear-gradient()` or `var()`, is
+    /// left for the applications to evaluate.
+    const SASS_FUNCTIONS: &'static [&'static str] = &[
+        "adjust-color",
+        "adjust-hue",
+        "alpha",
+        "blue",
+        "change-color",
+        "complement",
+        "contrast-color",
+        "contrast_color",
+        "darken",
+        "desaturate",
+        "fade-in",
+        "fade-out",
+        "green",
+        "hue",
+        "ie-hex-str",
+        "if",
+        "index",
+        "join",
+        "length",
+        "lighten",
+        "lightness",
+        "map-get",
+        "map-has-key",
+        "map-keys",
+        "map-merge",
+        "map-remove",
+        "map-values",
+        "mix",
+        "nth",
+        "opacify",
+        "percentage",
+        "quote",
+        "red",
+        "saturation",
+        "scale-color",
+        "str-index",
+        "str-insert",
+        "str-length",
+        "str-slice",
+        "to-lower-case",
+        "to-upper-case",
+        "transparentize",
+        "type-of",
+        "unique-id",
+        "unit",
+        "unitless",
+        "unquote",
+    ];
+
+    /// How many seconds changing the wallpaper may take, unless configured otherwise.
+    const DEFAULT_WALLPAPER_TIMEOUT_SECONDS: u64 = 15;
+
+    /// Environment variable that turns off reloading applications when set to `1`, like
+    /// [`ApplyOptions::no_reload`], e.g. in a container without Hyprland.
+    pub const DEV_MODE_ENV: &'static str = "NORLYK_DEV";
+
+    /// How long a single `hyprctl` call may take before it is killed.
+    const HYPRCTL_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Sets the theme by configuring Hypr, Waybar, and wallpaper settings. Only the components
+    /// enabled in `options` are changed.
+    ///
+    /// Every step is attempted even if an earlier one fails, so that as much of the theme as
+    /// possible is applied.
+    ///
+    /// The theme is applied even if it is already the current theme. Callers that want to skip
+    /// it then, check [`Self::is_current()`] first.
+    ///
+    /// With an [output directory](ApplyOptions::output_dir), only the configuration files are
+    /// written. The current theme is left unchanged, and the hooks are not run.
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - The `HOME` environment variable is not set or inaccessible.
+    /// Returns [`ApplyError::Failed`] if nothing was applied, because:
     /// - The theme directory or theme variables file cannot be found.
     /// - The SCSS variables cannot be parsed from the theme file.
+    /// - The wallpaper is enabled, but the theme has no wallpapers.
+    /// - The `pre-apply` hook failed.
+    /// - Every step failed, when writing to an output directory.
+    ///
+    /// Returns [`ApplyError::Partial`] if `current` was switched or some steps succeeded, but:
     /// - Application of a theme to a program failed
     /// - Setting the wallpaper fails after multiple retry attempts.
-    pub fn set_current_theme(theme: &Theme) -> Result<(), String> {
-        let context = Self::create_context(theme)
-            .map_err(|e| format!("Could not create theme context: {e}"))?;
+    /// - The `post-apply` hook failed.
+    pub fn set_current_theme(
+        settings: &Settings,
+        theme: &Theme,
+        options: &ApplyOptions,
+    ) -> Result<(), ApplyError> {
+        Self::set_current_theme_with_progress(settings, theme, options, &mut |_| {})
+    }
+
+    /// Like [`Self::set_current_theme()`], but calls `on_step` after every step, such as applying
+    /// the theme to one application, e.g. to print how long each step took.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`Self::set_current_theme()`].
+    pub fn set_current_theme_with_progress(
+        settings: &Settings,
+        theme: &Theme,
+        options: &ApplyOptions,
+        on_step: &mut dyn FnMut(&StepResult),
+    ) -> Result<(), ApplyError> {
+        let mut steps = StepRecorder {
+            steps: Vec::new(),
+            on_step,
+        };
+
+        if !ApplyLog::is_enabled() {
+            return Self::apply_theme(settings, theme, options, &mut steps);
+        }
+
+        CommandRunner::start_recording();
+        let result = Self::apply_theme(settings, theme, options, &mut steps);
+        let commands = CommandRunner::stop_recording();
+
+        ApplyLog::write(&settings.paths, theme, &steps.steps, &result, &commands);
+
+        result
+    }
+
+    /// Checks whether applying the theme with the options would change nothing, because it is the
+    /// current theme, `current` is already a copy or a symlink as requested, and the files are
+    /// not written to an output directory.
+    #[must_use]
+    pub fn is_current(settings: &Settings, theme: &Theme, options: &ApplyOptions) -> bool {
+        options.output_dir.is_none()
+            && settings.paths.current_theme().is_symlink() != options.copy_current
+            && Self::current_theme_directory(settings).as_ref() == Some(&theme.directory_path)
+    }
+
+    fn apply_theme(
+        settings: &Settings,
+        theme: &Theme,
+        options: &ApplyOptions,
+        steps: &mut StepRecorder<'_>,
+    ) -> Result<(), ApplyError> {
+        let context = Self::create_context(settings, theme, options)
+            .map_err(|e| ApplyError::Failed(format!("Could not create theme context: {e}")))?;
+
+        // The wallpaper is part of the running session, like the applications
+        let changes_wallpaper =
+            options.is_enabled(Component::Wallpaper) && context.reloads_applications();
+
+        // Checked up front, so that the colors are not changed when the wallpaper cannot be
+        if changes_wallpaper && theme.wallpaper_color.is_none() {
+            let wallpapers = Self::list_wallpapers(settings, &theme.wallpapers_path())
+                .map_err(ApplyError::Failed)?;
+
+            if wallpapers.is_empty() {
+                return Err(ApplyError::Failed(format!(
+                    "No wallpapers found in {}",
+                    theme.wallpapers_path().display()
+                )));
+            }
+        }
+
+        // Files written to an output directory are only staged, not applied
+        let changes_current = options.output_dir.is_none();
+
+        if changes_current {
+            steps
+                .run(Hook::PreApply.file_name(), || {
+                    HookService::run(Hook::PreApply, &context)
+                })
+                .map_err(ApplyError::Failed)?;
+
+            Self::update_current_theme(settings, theme, options.copy_current)
+                .map_err(ApplyError::Failed)?;
+        }
 
-        Symlink::create(&theme.directory_path, &Paths::current_theme()?)?;
+        let mut attempted_steps = 0;
+        let mut errors = Vec::new();
 
+        // Every step is attempted, and the failures are reported together below
         for themer in Self::themers() {
-            themer
-                .apply(&context)
-                .map_err(|e| format!("Could not apply theme: {e}"))?;
+            let component = themer.component();
+
+            if !options.is_enabled(component) {
+                continue;
+            }
+
+            attempted_steps += 1;
+            if let Err(e) = steps.run(&component.to_string(), || {
+                themer
+                    .apply(&context)
+                    .map_err(|e| format!("Could not apply theme to {component}: {e}"))
+            }) {
+                errors.push(e);
+            }
+        }
+
+        if changes_wallpaper {
+            attempted_steps += 1;
+            if let Err(e) = steps.run(&Component::Wallpaper.to_string(), || {
+                Self::change_wallpaper(settings).map_err(|e| e.to_string())
+            }) {
+                errors.push(e);
+            }
         }
 
-        Self::change_wallpaper()?;
+        if !errors.is_empty() {
+            // Once `current` was switched, the theme was applied to whatever reads it
+            if errors.len() == attempted_steps && !changes_current {
+                return Err(ApplyError::Failed(errors.join("\n")));
+            }
 
-        Ok(())
+            return Err(ApplyError::Partial(errors));
+        }
+
+        if !changes_current {
+            return Ok(());
+        }
+
+        steps
+            .run(Hook::PostApply.file_name(), || {
+                HookService::run(Hook::PostApply, &context)
+            })
+            .map_err(|e| ApplyError::Partial(vec![e]))
+    }
+
+    /// Whether applying a theme with `options` tells the running applications to pick it up, which
+    /// it does not with `--no-reload`, `--output-dir` or in [dev mode](Self::DEV_MODE_ENV).
+    #[must_use]
+    pub fn reloads_applications(options: &ApplyOptions) -> bool {
+        !options.no_reload && options.output_dir.is_none() && !Self::is_dev_mode()
+    }
+
+    fn is_dev_mode() -> bool {
+        env::var(Self::DEV_MODE_ENV).is_ok_and(|value| value == "1")
+    }
+
+    fn create_context<'a>(
+        settings: &'a Settings,
+        theme: &'a Theme,
+        options: &ApplyOptions,
+    ) -> Result<ThemeContext<'a>, String> {
+        let mut variables = Self::get_variables(theme)?;
+
+        if options.sort_variables {
+            variables.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        Ok(ThemeContext {
+            theme,
+            settings,
+            variables,
+            file_system: if options.verbose {
+                &VerboseFs
+            } else {
+                &SystemFs
+            },
+            output_dir: options.output_dir.clone(),
+            reload_applications: !options.no_reload && !Self::is_dev_mode(),
+            waybar_reload: options.waybar_reload,
+        })
+    }
+
+    /// Finds problems that would keep the theme from being fully applied with `options`, such as
+    /// missing wallpapers or Kitty template placeholders that the theme has no variables for.
+    /// Problems with components that `options` leave untouched are not reported.
+    #[must_use]
+    pub fn find_issues(
+        settings: &Settings,
+        theme: &Theme,
+        options: &ApplyOptions,
+    ) -> Vec<ThemeIssue> {
+        if !theme.is_complete() {
+            return vec![ThemeIssue::MissingVariablesFile(
+                theme.get_theme_variables_css_file_path(),
+            )];
+        }
+
+        let mut issues = Vec::new();
+
+        // The wallpaper is part of the running session, like the applications
+        if options.is_enabled(Component::Wallpaper) && Self::reloads_applications(options) {
+            match Self::list_wallpapers(settings, &theme.wallpapers_path()) {
+                Ok(wallpapers) if wallpapers.is_empty() && theme.wallpaper_color.is_none() => {
+                    issues.push(ThemeIssue::NoWallpapers(theme.wallpapers_path()));
+                }
+                Ok(_) => {}
+                Err(e) => issues.push(ThemeIssue::InvalidWallpapers(e)),
+            }
+        }
+
+        if let Some(thumbnail_path) = &theme.thumbnail_path
+            && !TerminalGraphics::is_png(thumbnail_path)
+        {
+            issues.push(ThemeIssue::UnsupportedThumbnail(thumbnail_path.clone()));
+        }
+
+        match Self::get_variables(theme) {
+            Ok(variables) => {
+                // A missing template is reported when applying, as it is not specific to the theme
+                if options.is_enabled(Component::Kitty)
+                    && let Ok(placeholders) = KittyThemer::unresolved_placeholders(
+                        &settings.config,
+                        &variables,
+                        &SystemFs,
+                    )
+                    && !placeholders.is_empty()
+                {
+                    issues.push(ThemeIssue::UnresolvedPlaceholders(placeholders));
+                }
+            }
+            Err(e) => issues.push(ThemeIssue::InvalidVariables(e)),
+        }
+
+        issues
+    }
+
+    /// Fills in the placeholders of an arbitrary template with the theme's variables, the same way
+    /// Kitty's `theme-template.conf` is, and writes the result to `output_path`. No application
+    /// is reloaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the theme's variables cannot be read, the template cannot be read, a
+    /// variable is invalid, or the output cannot be written.
+    pub fn render_template(
+        theme: &Theme,
+        template_path: &Path,
+        output_path: &Path,
+    ) -> Result<(), String> {
+        let variables = Self::get_variables(theme)?;
+        let template = fs::read_to_string(template_path)
+            .map_err(|e| format!("Could not read {}: {e}", template_path.display()))?;
+        let output = Template::render(&template, &variables, output_path)?;
+
+        fs::write(output_path, output)
+            .map_err(|e| format!("Could not write {}: {e}", output_path.display()))
+    }
+
+    /// Gets the theme's SCSS variables as name and value pairs, in the order they are declared,
+    /// including the variables of any used files.
+    ///
+    /// If the theme extends another theme, the variables of that theme come first, and the
+    /// theme's own variables replace those with the same name. A theme extending another theme
+    /// does not need a variables file of its own.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the variables file does not exist, cannot be read, or contains no
+    /// variables, or if an extended theme does not exist or the themes extend each other in a
+    /// cycle.
+    pub fn get_variables(theme: &Theme) -> Result<Vec<(String, String)>, String> {
+        Self::resolve_variables(theme, &mut Vec::new())
     }
 
-    fn create_context(theme: &Theme) -> Result<ThemeContext<'_>, String> {
+    /// Resolves the variables of the theme and the themes it extends, where `extended_by` holds the
+    /// directory names of the themes extending this one, for detecting cycles.
+    fn resolve_variables(
+        theme: &Theme,
+        extended_by: &mut Vec<String>,
+    ) -> Result<Vec<(String, String)>, String> {
+        let dir_name = theme.dir_name();
+
+        if extended_by.contains(&dir_name) {
+            return Err(format!(
+                "The themes extend each other in a cycle: {} -> {dir_name}",
+                extended_by.join(" -> ")
+            ));
+        }
+
+        extended_by.push(dir_name);
+
         let path = &theme.get_theme_variables_css_file_path();
-        let variables = Self::collect_variables(path)?;
 
-        Ok(ThemeContext { theme, variables })
+        let Some(parent_name) = &theme.extends else {
+            if !path.is_file() {
+                return Err(format!(
+                    "Theme variables file not found: {}",
+                    path.display()
+                ));
+            }
+
+            return Self::read_variables_file(path);
+        };
+
+        Self::validate_theme_name(parent_name)?;
+
+        let parent = theme
+            .directory_path
+            .parent()
+            .and_then(|themes_path| Self::load_theme(&themes_path.join(parent_name)))
+            .ok_or_else(|| {
+                format!(
+                    "The theme '{parent_name}' extended by {} does not exist",
+                    theme.name
+                )
+            })?
+            .map_err(|skipped| {
+                format!(
+                    "The theme '{parent_name}' extended by {} cannot be loaded. {}",
+                    theme.name, skipped.error
+                )
+            })?;
+
+        let mut variables = Self::resolve_variables(&parent, extended_by)?;
+
+        if path.is_file() {
+            for (name, value) in Self::read_variables_file(path)? {
+                match variables.iter_mut().find(|variable| variable.0 == name) {
+                    Some(variable) => variable.1 = value,
+                    None => variables.push((name, value)),
+                }
+            }
+        }
+
+        Ok(variables)
     }
 
     fn themers() -> Vec<Box<dyn Themer>> {
         vec![
             Box::new(HyprThemer),
             Box::new(KittyThemer),
+            Box::new(WeztermThemer),
             Box::new(WaybarThemer),
             Box::new(BtopThemer),
             Box::new(GtkThemer),
@@ -75,186 +543,2278 @@ impl ThemeService {
         ]
     }
 
-    /// Get all available themes by reading the directory returned by [`Paths::config_path()`].
+    /// Opens the theme's directory in the default file manager using `xdg-open`.
     ///
     /// # Errors
     ///
-    /// The theme directory cannot be found.
+    /// Returns an error naming the attempted command if `xdg-open` cannot be executed.
+    pub fn open_directory(theme: &Theme) -> Result<(), String> {
+        Command::new("xdg-open")
+            .arg(&theme.directory_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                format!(
+                    "Could not execute 'xdg-open {}': {e}",
+                    theme.directory_path.display()
+                )
+            })?;
+
+        Ok(())
+    }
+
+    /// Opens the theme's variables file in the editor given by the `EDITOR` environment variable,
+    /// and waits for the editor to exit. `EDITOR` is split like a shell would, so it can have
+    /// arguments, e.g. `code --wait`.
+    ///
+    /// # Errors
     ///
-    pub fn get_available_themes() -> Result<Vec<Theme>, String> {
-        let config_path = &Paths::config_path()?;
+    /// Returns an error if `EDITOR` is not set or has an unclosed quote, or naming the attempted
+    /// command if the editor cannot be executed.
+    pub fn edit_variables(theme: &Theme) -> Result<(), String> {
+        let editor = env::var("EDITOR")
+            .ok()
+            .filter(|editor| !editor.trim().is_empty())
+            .ok_or_else(|| "The EDITOR environment variable is not set".to_string())?;
 
-        let files = fs::read_dir(config_path)
-            .map_err(|e| format!("Failed to read files in the config directory: {e}"))?;
+        let variables_file_path = theme.get_theme_variables_css_file_path();
 
-        let mut themes: Vec<Theme> = files
-            .filter_map(|file| {
-                let entry = file.ok()?;
-                let path = entry.path();
+        let status = CommandRunner::parse(&editor)?
+            .arg(&variables_file_path)
+            .status()
+            .map_err(|e| {
+                format!(
+                    "Could not execute '{editor} {}': {e}",
+                    variables_file_path.display()
+                )
+            })?;
 
-                if !path.is_dir() || path.is_symlink() {
-                    return None;
-                }
+        if !status.success() {
+            return Err(format!(
+                "'{editor} {}' failed with {status}",
+                variables_file_path.display()
+            ));
+        }
 
-                let meta_file_path = path.join("meta.toml");
-                let contents = fs::read_to_string(meta_file_path).ok()?;
-                let meta: RawThemeMetadata = toml::from_str(&contents).ok()?;
-
-                Some(Theme::new(
-                    meta.name.as_str(),
-                    meta.description.as_str(),
-                    path,
-                    meta.btop_theme_path.map(PathBuf::from),
-                    meta.color_scheme,
-                    meta.gtk_theme.as_str(),
-                ))
-            })
-            .collect();
+        Ok(())
+    }
 
-        themes.sort_by(|t1, t2| t1.name.cmp(&t2.name));
+    /// Checks that a theme directory name given by the user, such as the name of a duplicate or of
+    /// an extended theme, cannot be used to escape the theme directory. Names only compared with
+    /// the themes do not need to be checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is empty, contains a path separator or `..`, or is an absolute
+    /// path.
+    pub fn validate_theme_name(name: &str) -> Result<(), String> {
+        if name.trim().is_empty() {
+            return Err("The theme name cannot be empty".to_string());
+        }
+
+        if name.contains(['/', '\\']) || name.contains("..") || Path::new(name).is_absolute() {
+            return Err(format!(
+                "Invalid theme name '{name}': it cannot contain path separators or '..'"
+            ));
+        }
 
-        Ok(themes)
+        Ok(())
     }
 
-    fn collect_variables(path: &Path) -> Result<Vec<(String, String)>, String> {
-        let Ok(content) = fs::read_to_string(path) else {
-            return Err(format!("Could not read file: {}", path.display()));
-        };
+    /// Records the theme as the default theme, returned by [`Self::get_default_theme()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the default theme file cannot be written.
+    pub fn set_default_theme(settings: &Settings, theme: &Theme) -> Result<(), String> {
+        let default_theme_file_path = settings.paths.default_theme_file();
 
-        let mut variables: Vec<(String, String)> = Vec::new();
-        let use_import_regex = Regex::new(r#"^@use "(:?.*)";$"#).unwrap();
+        fs::write(&default_theme_file_path, format!("{}\n", theme.dir_name()))
+            .map_err(|e| format!("Could not write {}: {e}", default_theme_file_path.display()))
+    }
 
-        for line in content.lines() {
-            let trimmed = line.trim();
+    /// Gets the theme recorded by [`Self::set_default_theme()`]. If no default theme is recorded,
+    /// the last applied theme is returned instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the themes cannot be read, if the recorded default theme no longer
+    /// exists, or if there is neither a default theme nor a last applied theme.
+    pub fn get_default_theme(settings: &Settings) -> Result<Theme, String> {
+        let themes = Self::get_available_themes(settings)?;
+        let default_theme_file_path = settings.paths.default_theme_file();
 
-            if trimmed.starts_with("@use") {
-                let Some(relative_used_path) = use_import_regex
-                    .captures(line)
-                    .and_then(|caps| caps.get(1))
-                    .map(|m| format!("{}.scss", m.as_str()))
-                else {
-                    continue;
-                };
+        if let Ok(contents) = fs::read_to_string(&default_theme_file_path) {
+            let dir_name = contents.trim();
 
-                let parent = Path::new(path).parent().unwrap();
-                let absolute_used_path = parent.join(relative_used_path);
+            return themes
+                .into_iter()
+                .find(|theme| theme.dir_name() == dir_name)
+                .ok_or_else(|| format!("The default theme '{dir_name}' does not exist"));
+        }
 
-                variables.extend(Self::collect_variables(&absolute_used_path)?);
+        Self::get_current_theme(settings, themes)
+            .ok_or_else(|| "No default theme is set, and no theme has been applied".to_string())
+    }
 
-                continue;
-            }
+    /// Records the theme as the one last selected in the theme picker, returned by
+    /// [`Self::last_selection()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the last selection file cannot be written.
+    pub fn set_last_selection(settings: &Settings, theme: &Theme) -> Result<(), String> {
+        let last_selection_file_path = settings.paths.last_selection_file();
 
-            // Match pattern: $variableName: value;
-            let Some(dollar_pos) = trimmed.find('$') else {
-                continue;
-            };
+        fs::write(&last_selection_file_path, format!("{}\n", theme.dir_name())).map_err(|e| {
+            format!(
+                "Could not write {}: {e}",
+                last_selection_file_path.display()
+            )
+        })
+    }
 
-            let Some(colon_pos) = trimmed.find(':') else {
-                continue;
-            };
+    /// Gets the directory name of the theme recorded by [`Self::set_last_selection()`], if any.
+    #[must_use]
+    pub fn last_selection(settings: &Settings) -> Option<String> {
+        let contents = fs::read_to_string(settings.paths.last_selection_file()).ok()?;
+        let dir_name = contents.trim();
 
-            let Some(semicolon_pos) = trimmed.find(';') else {
-                continue;
-            };
+        (!dir_name.is_empty()).then(|| dir_name.to_string())
+    }
 
-            let var_name = trimmed[dollar_pos + 1..colon_pos].trim().to_string();
+    /// Duplicates the theme into a new directory named `dir_name` next to it, and names the copy
+    /// after the directory, so that variants of a theme can be created quickly.
+    ///
+    /// Returns the path of the copy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory name is invalid or already exists, or if the theme
+    /// cannot be copied or the copy's metadata cannot be rewritten.
+    pub fn duplicate_theme(theme: &Theme, dir_name: &str) -> Result<PathBuf, String> {
+        Self::validate_theme_name(dir_name)?;
 
-            let var_value = trimmed[colon_pos + 1..semicolon_pos]
-                .trim()
-                .chars()
-                .filter(|c| !c.is_whitespace())
-                .collect::<String>();
+        let destination = theme
+            .directory_path
+            .parent()
+            .ok_or_else(|| format!("{} has no parent", theme.directory_path.display()))?
+            .join(dir_name);
 
-            variables.push((var_name, var_value));
+        if Symlink::exists(&destination)? {
+            return Err(format!("{} already exists", destination.display()));
         }
 
-        if variables.is_empty() {
-            return Err("No SCSS variables found".to_string());
+        Directory::copy(&theme.directory_path, &destination)?;
+        Self::rename_metadata(&destination, dir_name)?;
+
+        Ok(destination)
+    }
+
+    /// Finds the theme that `current` refers to, i.e. the last applied theme.
+    #[must_use]
+    pub fn get_current_theme(settings: &Settings, themes: Vec<Theme>) -> Option<Theme> {
+        let target = Self::current_theme_directory(settings)?;
+
+        themes
+            .into_iter()
+            .find(|theme| theme.directory_path == target)
+    }
+
+    /// Gets the directory of the theme that `current` refers to, either as the target of the
+    /// symlink, or as recorded in [`Self::COPIED_FROM_FILE_NAME`] if the theme was copied.
+    #[must_use]
+    pub fn current_theme_directory(settings: &Settings) -> Option<PathBuf> {
+        let current_theme_path = settings.paths.current_theme();
+
+        if let Ok(target) = fs::read_link(&current_theme_path) {
+            return Some(target);
         }
 
-        Ok(variables)
+        let copied_from =
+            fs::read_to_string(current_theme_path.join(Self::COPIED_FROM_FILE_NAME)).ok()?;
+
+        Some(PathBuf::from(copied_from.trim_end_matches('\n')))
     }
 
-    /// Reloads the wallpaper by selecting a random image from the current theme's wallpaper directory
-    /// and setting it using hyprpaper.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if:
-    /// - The `HOME` environment variable is not set or inaccessible.
-    /// - The wallpaper directory cannot be read or contains no valid image files.
-    /// - The hyprctl command fails to execute or returns an error after multiple retry attempts.
-    pub fn change_wallpaper() -> Result<(), String> {
-        let wallpaper_dir_path = Paths::current_theme()?.join("wallpapers");
-        let wallpaper_file_path = Self::get_random_image_file(&wallpaper_dir_path)?;
+    /// Removes `current` if it is a copy of a theme, which is recognized by its
+    /// [`Self::COPIED_FROM_FILE_NAME`] file. A symbolic link is left for the caller to replace,
+    /// and any other directory is an error, so that a directory the theme picker did not create is
+    /// never deleted.
+    fn remove_copied_current_theme(current_theme_path: &Path) -> Result<(), String> {
+        if !fs::symlink_metadata(current_theme_path).is_ok_and(|metadata| metadata.is_dir()) {
+            return Ok(());
+        }
 
-        let max_attempts = 5;
-        let mut error: Option<Error> = None;
+        Self::ensure_current_theme_replaceable(current_theme_path)?;
 
-        let wallpaper_arg = format!(",{}", wallpaper_file_path.display());
+        fs::remove_dir_all(current_theme_path)
+            .map_err(|e| format!("Failed to remove {}: {e}", current_theme_path.display()))
+    }
 
-        for _ in 1..=max_attempts {
-            let output = Command::new("hyprctl")
-                .arg("hyprpaper")
-                .arg("wallpaper")
-                .arg(&wallpaper_arg)
-                .output();
-
-            match output {
-                Ok(result) => {
-                    let response = String::from_utf8_lossy(&result.stdout).trim().to_string();
-                    if response.is_empty() {
-                        return Ok(());
-                    }
+    /// Checks that `current` is not a directory the theme picker did not create, which is never
+    /// replaced. Symbolic links, copies of themes and a missing `current` can be replaced.
+    fn ensure_current_theme_replaceable(current_theme_path: &Path) -> Result<(), String> {
+        if fs::symlink_metadata(current_theme_path).is_ok_and(|metadata| metadata.is_dir())
+            && !current_theme_path
+                .join(Self::COPIED_FROM_FILE_NAME)
+                .is_file()
+        {
+            return Err(format!(
+                "{} is a directory that was not copied by the theme picker, remove it to apply a theme",
+                current_theme_path.display()
+            ));
+        }
 
-                    error = Some(Error::other(response));
-                }
-                Err(e) => error = Some(e),
+        Ok(())
+    }
+
+    /// Copies the theme into `current`, replacing whichever kind of `current` was there before.
+    /// The theme is copied into a temporary sibling of `current` first, which is then swapped in
+    /// by renaming, so that `current` is never left missing or half-copied if the process is
+    /// killed while copying.
+    fn copy_current_theme(theme: &Theme, current_theme_path: &Path) -> Result<(), String> {
+        Self::ensure_current_theme_replaceable(current_theme_path)?;
+
+        let temporary_path =
+            current_theme_path.with_file_name(format!(".current.{}.tmp", process::id()));
+        let replaced_path =
+            current_theme_path.with_file_name(format!(".current.{}.old", process::id()));
+
+        // Left over if an earlier run was killed while copying or swapping
+        Self::remove_leftover(&temporary_path)?;
+        Self::remove_leftover(&replaced_path)?;
+
+        let copied = Directory::copy(&theme.directory_path, &temporary_path).and_then(|()| {
+            let copied_from_file_path = temporary_path.join(Self::COPIED_FROM_FILE_NAME);
+            fs::write(
+                &copied_from_file_path,
+                format!("{}\n", theme.directory_path.display()),
+            )
+            .map_err(|e| format!("Could not write {}: {e}", copied_from_file_path.display()))
+        });
+
+        if let Err(e) = copied {
+            let _ = Self::remove_leftover(&temporary_path);
+            return Err(e);
+        }
+
+        // A directory cannot be renamed over a symbolic link or a non-empty directory, so the old
+        // `current` is moved aside first, and moved back if the copy cannot take its place
+        let has_current = Symlink::exists(current_theme_path)?;
+
+        if has_current {
+            fs::rename(current_theme_path, &replaced_path).map_err(|e| {
+                let _ = Self::remove_leftover(&temporary_path);
+                format!("Failed to move {} aside: {e}", current_theme_path.display())
+            })?;
+        }
+
+        if let Err(e) = fs::rename(&temporary_path, current_theme_path) {
+            if has_current {
+                let _ = fs::rename(&replaced_path, current_theme_path);
             }
+            let _ = Self::remove_leftover(&temporary_path);
 
-            thread::sleep(Duration::from_secs(1));
+            return Err(format!(
+                "Failed to replace {} with a copy of {}: {e}",
+                current_theme_path.display(),
+                theme.directory_path.display()
+            ));
         }
 
-        if let Some(error) = error {
-            return Err(format!("Failed to set wallpaper: {error}"));
+        Self::remove_leftover(&replaced_path)
+    }
+
+    /// Removes a temporary file, symbolic link or directory created by the theme picker, if it
+    /// exists.
+    fn remove_leftover(path: &Path) -> Result<(), String> {
+        let result = match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+            Ok(_) => fs::remove_file(path),
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => Err(e),
+        };
+
+        result.map_err(|e| format!("Failed to remove {}: {e}", path.display()))
+    }
+
+    /// Points `current` at the theme, either by symlinking it or by copying its contents,
+    /// replacing whichever kind of `current` was there before.
+    fn update_current_theme(settings: &Settings, theme: &Theme, copy: bool) -> Result<(), String> {
+        let current_theme_path = settings.paths.current_theme();
+
+        // `current` is what the generated configuration files and the wallpaper are read through
+        Self::ensure_within_themes_dir(&settings.paths, &theme.directory_path)?;
+
+        if copy {
+            return Self::copy_current_theme(theme, &current_theme_path);
         }
 
-        Err("Unknown error".to_string())
+        Self::remove_copied_current_theme(&current_theme_path)?;
+        Symlink::create(&theme.directory_path, &current_theme_path)
+    }
+
+    /// Get all available themes by reading the directory returned by [`Paths::config_path()`] of
+    /// the settings. Themes that cannot be loaded are left out, see [`Self::load_themes()`] to
+    /// report them.
+    ///
+    /// # Errors
+    ///
+    /// The theme directory cannot be found.
+    ///
+    pub fn get_available_themes(settings: &Settings) -> Result<Vec<Theme>, String> {
+        Self::load_themes(settings).map(|loaded| loaded.themes)
     }
 
-    fn get_random_image_file(path: &Path) -> Result<PathBuf, String> {
-        // Read all image files from the theme directory
-        let entries =
-            fs::read_dir(path).map_err(|e| format!("Failed to read theme directory: {e}"))?;
+    /// Like [`Self::get_available_themes()`], but also returns the directories that have a metadata
+    /// file, but could not be loaded as a theme, so that they can be reported.
+    ///
+    /// # Errors
+    ///
+    /// The theme directory cannot be found.
+    pub fn load_themes(settings: &Settings) -> Result<LoadedThemes, String> {
+        let config_path = settings.paths.config_path();
+
+        let files = fs::read_dir(config_path)
+            .map_err(|e| format!("Failed to read files in the config directory: {e}"))?;
+
+        let ignore_patterns = Self::read_ignore_patterns(config_path);
 
-        let image_files: Vec<PathBuf> = entries
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
+        let theme_paths: Vec<PathBuf> = files
+            .filter_map(|file| {
+                let entry = file.ok()?;
                 let path = entry.path();
 
-                // Check if it's a file with common image extensions
-                if path.is_file()
-                    && let Some(extension) = path.extension()
+                // The current theme is either a symlink or a copy of another theme
+                if !path.is_dir()
+                    || path.is_symlink()
+                    || path.join(Self::COPIED_FROM_FILE_NAME).exists()
                 {
-                    let ext_str = extension.to_string_lossy().to_lowercase();
-                    if matches!(ext_str.as_str(), "png" | "jpg" | "jpeg" | "bmp") {
-                        return Some(path);
-                    }
+                    return None;
                 }
-                None
-            })
-            .collect();
 
-        if image_files.is_empty() {
-            return Err("No image files found in theme directory".to_string());
-        }
+                let dir_name = entry.file_name();
+                let dir_name = dir_name.to_string_lossy();
+
+                if dir_name.starts_with('_')
+                    || dir_name.starts_with('.')
+                    || ignore_patterns
+                        .iter()
+                        .any(|pattern| pattern.is_match(&dir_name))
+                {
+                    return None;
+                }
+
+                Some(path)
+            })
+            .collect();
+
+        // Reading and parsing the metadata is spread across threads, so that a cold cache with many
+        // themes doesn't delay the startup of the TUI
+        let thread_count = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let chunk_size = theme_paths.len().div_ceil(thread_count).max(1);
+
+        let results: Vec<Result<Theme, SkippedTheme>> = thread::scope(|scope| {
+            let handles: Vec<_> = theme_paths
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .filter_map(|path| Self::load_theme(path))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            // A panic while loading a theme is a bug, which must not make its themes disappear
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        });
+
+        let (themes, skipped): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+        let mut themes: Vec<Theme> = themes.into_iter().filter_map(Result::ok).collect();
+        let skipped = skipped.into_iter().filter_map(Result::err).collect();
+
+        themes.sort_by(|t1, t2| t1.name.cmp(&t2.name));
+
+        Ok(LoadedThemes { themes, skipped })
+    }
+
+    /// Loads the theme in the given directory, or `None` if the directory does not contain a
+    /// metadata file.
+    fn load_theme(path: &Path) -> Option<Result<Theme, SkippedTheme>> {
+        // Directories without a metadata file are not themes, such as `wallpapers/` collections
+        if !ThemeMetadata::exists(path) {
+            return None;
+        }
+
+        Some(Theme::from_dir(path).map_err(|error| SkippedTheme {
+            path: path.to_path_buf(),
+            error,
+        }))
+    }
+
+    /// Reads the directory names and globs listed in the `.norlykignore` file in the config
+    /// directory, one per line. Empty lines and lines starting with `#` are skipped.
+    fn read_ignore_patterns(config_path: &Path) -> Vec<Regex> {
+        let Ok(contents) = fs::read_to_string(config_path.join(".norlykignore")) else {
+            return Vec::new();
+        };
+
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|glob| {
+                let pattern = regex::escape(glob.trim_end_matches('/'))
+                    .replace(r"\*", ".*")
+                    .replace(r"\?", ".");
+
+                Regex::new(&format!("^{pattern}$")).ok()
+            })
+            .collect()
+    }
+
+    /// Changes the name in the metadata file of the theme in the given directory, keeping the
+    /// file's format. The comments and the order of the keys of a `meta.toml` are kept as well.
+    fn rename_metadata(theme_path: &Path, name: &str) -> Result<(), String> {
+        let (file_name, contents) = ThemeMetadata::read_file(theme_path)
+            .ok_or_else(|| format!("No metadata file found in {}", theme_path.display()))?;
+        let metadata_file_path = theme_path.join(file_name);
+        let invalid =
+            |e: String| format!("Invalid metadata in {}: {e}", metadata_file_path.display());
+
+        let renamed = match file_name {
+            "meta.toml" => {
+                let mut document: toml_edit::DocumentMut = contents
+                    .parse()
+                    .map_err(|e: toml_edit::TomlError| invalid(e.to_string()))?;
+                // Replacing the value would drop a comment after it, which belongs to its decor
+                match document
+                    .get_mut("name")
+                    .and_then(toml_edit::Item::as_value_mut)
+                {
+                    Some(value) => {
+                        let decor = value.decor().clone();
+                        *value = name.into();
+                        *value.decor_mut() = decor;
+                    }
+                    None => document["name"] = toml_edit::value(name),
+                }
+
+                document.to_string()
+            }
+            "meta.json" => {
+                let mut object: serde_json::Map<String, serde_json::Value> =
+                    serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+                object.insert("name".to_string(), name.into());
+                serde_json::to_string_pretty(&object).map_err(|e| invalid(e.to_string()))?
+            }
+            _ => {
+                let mut mapping: serde_yaml_ng::Mapping =
+                    serde_yaml_ng::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+                mapping.insert("name".into(), name.into());
+                serde_yaml_ng::to_string(&mapping).map_err(|e| invalid(e.to_string()))?
+            }
+        };
+
+        fs::write(&metadata_file_path, renamed)
+            .map_err(|e| format!("Could not write {}: {e}", metadata_file_path.display()))
+    }
+
+    /// Reads the variables from a theme's variables file, which holds CSS custom properties if it
+    /// is a `.css` file, and SCSS variables otherwise.
+    fn read_variables_file(path: &Path) -> Result<Vec<(String, String)>, String> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("css"))
+        {
+            return Self::collect_css_variables(path);
+        }
+
+        Self::collect_variables(path)
+    }
+
+    /// Collects the custom properties declared in the `:root` rules of a CSS file, such as
+    /// `--bg: #1f1f28;`, named without the leading dashes. A value that is only a `var(--name)`
+    /// reference is resolved against the properties declared before it.
+    fn collect_css_variables(path: &Path) -> Result<Vec<(String, String)>, String> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Err(format!("Could not read file: {}", path.display()));
+        };
+
+        let content = Self::strip_comments(&content);
+        let mut variables: Vec<(String, String)> = Vec::new();
+
+        for captures in ROOT_RULE_REGEX.captures_iter(&content) {
+            for declaration in captures[1].split(';') {
+                let Some((name, value)) = declaration.split_once(':') else {
+                    continue;
+                };
+
+                let Some(name) = name.trim().strip_prefix("--") else {
+                    continue;
+                };
+
+                let mut value = Self::normalize_unquoted_whitespace(value.trim());
+
+                if let Some(referenced) = CSS_VAR_REGEX.captures(&value) {
+                    let referenced_name = &referenced[1];
+
+                    // The last declaration of a property wins
+                    value = variables
+                        .iter()
+                        .rev()
+                        .find(|variable| variable.0 == referenced_name)
+                        .map(|variable| variable.1.clone())
+                        .ok_or_else(|| {
+                            format!("--{name} uses --{referenced_name}, which is not declared")
+                        })?;
+                }
+
+                variables.push((name.to_string(), value));
+            }
+        }
+
+        if variables.is_empty() {
+            return Err("No CSS custom properties found in :root".to_string());
+        }
+
+        Ok(variables)
+    }
+
+    fn collect_variables(path: &Path) -> Result<Vec<(String, String)>, String> {
+        Self::collect_variables_once(path, &mut HashMap::new())
+    }
+
+    /// Collects the SCSS variables of the file and the files it uses, parsing each file only
+    /// once, so that a partial used by several files is not read again for each of them. The
+    /// variables of the parsed files are kept in `parsed`, by canonical path. Files that use each
+    /// other in a cycle are an error, as in Sass.
+    fn collect_variables_once(
+        path: &Path,
+        parsed: &mut HashMap<PathBuf, Vec<(String, String)>>,
+    ) -> Result<Vec<(String, String)>, String> {
+        let canonical_path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+        if let Some(variables) = parsed.get(&canonical_path) {
+            // A parsed file always has variables, so no variables means the file is still being
+            // parsed, and uses itself through the files it uses
+            if variables.is_empty() {
+                return Err(format!("{} uses itself through @use", path.display()));
+            }
+
+            return Ok(variables.clone());
+        }
+
+        parsed.insert(canonical_path.clone(), Vec::new());
+
+        let Ok(content) = fs::read_to_string(path) else {
+            return Err(format!("Could not read file: {}", path.display()));
+        };
+
+        let content = Self::strip_comments(&content);
+        let mut variables: Vec<(String, String)> = Vec::new();
+        let mut namespaces: HashMap<String, Vec<(String, String)>> = HashMap::new();
+
+        // Statements are split on semicolons rather than lines, so that values can span lines
+        for statement in Self::split_statements(&content) {
+            let trimmed = statement.trim();
+
+            if trimmed.starts_with("@use") {
+                // Built-in modules such as `sass:map` have no variables
+                let Some(captures) = USE_IMPORT_REGEX
+                    .captures(trimmed)
+                    .filter(|captures| !captures[1].starts_with("sass:"))
+                else {
+                    continue;
+                };
+
+                let used_path = &captures[1];
+                let parent = Path::new(path).parent().unwrap();
+                let absolute_used_path = parent.join(format!("{used_path}.scss"));
+                let used_variables = Self::collect_variables_once(&absolute_used_path, parsed)?;
+
+                // Without `as`, the namespace is the file name, e.g. `system-variables` for
+                // `../system-variables`, while `as *` makes the variables available without one
+                let namespace = match captures.get(2) {
+                    Some(namespace) => namespace.as_str(),
+                    None => used_path.rsplit('/').next().unwrap_or(used_path),
+                };
+
+                if namespace != "*" {
+                    namespaces.insert(
+                        namespace.trim_start_matches('_').to_string(),
+                        used_variables.clone(),
+                    );
+                }
+
+                variables.extend(used_variables);
+
+                continue;
+            }
+
+            // Match pattern: $variableName: value;
+            let Some(dollar_pos) = trimmed.find('$') else {
+                continue;
+            };
+
+            let Some(colon_pos) = trimmed.find(':') else {
+                continue;
+            };
+
+            let var_name = trimmed[dollar_pos + 1..colon_pos].trim().to_string();
+
+            let var_value = Self::normalize_unquoted_whitespace(trimmed[colon_pos + 1..].trim());
+
+            let var_value = Self::resolve_value(&var_name, &var_value, &variables, &namespaces)?;
+
+            variables.push((var_name, var_value));
+        }
+
+        if variables.is_empty() {
+            return Err("No SCSS variables found".to_string());
+        }
+
+        parsed.insert(canonical_path, variables.clone());
+
+        Ok(variables)
+    }
+
+    /// Resolves `#{$font}` interpolations, then a `map.get($map, 'key')` or `map-get($map, key)`
+    /// value against the maps among the variables collected so far, a `contrast_color($bg)` or
+    /// `contrast_color($bg, $accent)` value to a color readable on `$bg`, and references to
+    /// variables of a used module such as `colors.$bg` against the module's variables. Other values
+    /// are returned as is, except for calls to the [Sass functions](Self::SASS_FUNCTIONS) that are
+    /// not part of CSS, since their result cannot be known without evaluating the SCSS.
+    fn resolve_value(
+        name: &str,
+        value: &str,
+        variables: &[(String, String)],
+        namespaces: &HashMap<String, Vec<(String, String)>>,
+    ) -> Result<String, String> {
+        let value = Self::interpolate(name, value, variables, namespaces)?;
+        if let Some(captures) = MAP_GET_REGEX.captures(&value) {
+            let map_name = &captures[2];
+            let key = Self::unquote(&captures[3]);
+
+            let scope = match captures.get(1) {
+                Some(namespace) => Self::namespace_variables(name, namespace.as_str(), namespaces)?,
+                None => variables,
+            };
+
+            // The last definition of a variable wins
+            let map = scope
+                .iter()
+                .rev()
+                .find(|variable| variable.0 == map_name)
+                .ok_or_else(|| format!("${name} uses the map ${map_name}, which is not defined"))?;
+
+            return Self::parse_map(&map.1)
+                .and_then(|entries| {
+                    entries
+                        .into_iter()
+                        .find(|(entry_key, _)| entry_key == key)
+                        .map(|(_, entry_value)| entry_value)
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "${name} uses the key '{key}', which the map ${map_name} does not contain"
+                    )
+                });
+        }
+
+        if let Some(captures) = CONTRAST_COLOR_REGEX.captures(&value) {
+            return Self::resolve_contrast_color(name, &captures[1], variables, namespaces);
+        }
+
+        let mut resolved = value.clone();
+
+        for captures in NAMESPACED_REFERENCE_REGEX.captures_iter(&value) {
+            let namespace = &captures[1];
+            let variable_name = &captures[2];
+
+            let (_, variable_value) = Self::namespace_variables(name, namespace, namespaces)?
+                .iter()
+                .rev()
+                .find(|variable| variable.0 == variable_name)
+                .ok_or_else(|| {
+                    format!("${name} uses {namespace}.${variable_name}, which is not defined")
+                })?;
+
+            resolved = resolved.replacen(&captures[0], variable_value, 1);
+        }
+
+        let value = resolved;
+        if let Some(function) = FUNCTION_CALL_REGEX
+            .captures_iter(&value)
+            .map(|captures| captures[1].to_string())
+            .find(|function| {
+                function.contains('.') || Self::SASS_FUNCTIONS.contains(&function.as_str())
+            })
+        {
+            return Err(format!(
+                "${name} calls the Sass function {function}(), only map.get(), map-get() and contrast_color() are evaluated"
+            ));
+        }
+
+        Ok(value)
+    }
+
+    /// Replaces every `#{$font}` or `#{fonts.$mono}` interpolation in the value with the value of
+    /// the variable, without its quotes, as Sass does, so that `"#{$font} Nerd Font"` becomes
+    /// `"JetBrains Mono Nerd Font"` for `$font: "JetBrains Mono"`.
+    fn interpolate(
+        name: &str,
+        value: &str,
+        variables: &[(String, String)],
+        namespaces: &HashMap<String, Vec<(String, String)>>,
+    ) -> Result<String, String> {
+        let mut interpolated = String::with_capacity(value.len());
+        let mut last_end = 0;
+
+        for captures in INTERPOLATION_REGEX.captures_iter(value) {
+            let (Some(whole), Some(expression)) = (captures.get(0), captures.get(1)) else {
+                continue;
+            };
+
+            let expression = expression.as_str().trim();
+            let Some(variable) = VARIABLE_REGEX.captures(expression) else {
+                return Err(format!(
+                    "${name} interpolates #{{{expression}}}, only variables can be interpolated"
+                ));
+            };
+
+            let variable_name = &variable[2];
+            let scope = match variable.get(1) {
+                Some(namespace) => Self::namespace_variables(name, namespace.as_str(), namespaces)?,
+                None => variables,
+            };
+
+            let (_, variable_value) = scope
+                .iter()
+                .rev()
+                .find(|v| v.0 == variable_name)
+                .ok_or_else(|| {
+                    format!("${name} interpolates {expression}, which is not defined")
+                })?;
+
+            interpolated.push_str(&value[last_end..whole.start()]);
+            interpolated.push_str(Self::unquote(variable_value));
+            last_end = whole.end();
+        }
+
+        interpolated.push_str(&value[last_end..]);
+
+        Ok(interpolated)
+    }
+
+    /// Normalizes the whitespace of a value outside of strings to single spaces between the parts
+    /// of the value, removing it around commas, colons and parentheses, so that `rgba(1, 2, 3, 1)`
+    /// becomes `rgba(1,2,3,1)` while `linear-gradient(#fff 0%, #000 100%)` keeps the spaces between
+    /// its colors and stops, and `"JetBrains Mono"` keeps its space.
+    fn normalize_unquoted_whitespace(value: &str) -> String {
+        let mut normalized = String::with_capacity(value.len());
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        let mut pending_space = false;
+
+        for c in value.chars() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+
+                normalized.push(c);
+                continue;
+            }
+
+            if c.is_whitespace() {
+                pending_space = true;
+                continue;
+            }
+
+            if pending_space
+                && !matches!(c, ',' | ':' | ')')
+                && !normalized.is_empty()
+                && !normalized.ends_with([',', ':', '('])
+            {
+                normalized.push(' ');
+            }
+            pending_space = false;
+
+            if c == '"' || c == '\'' {
+                quote = Some(c);
+            }
+
+            normalized.push(c);
+        }
+
+        normalized
+    }
+
+    /// Resolves the arguments of a `contrast_color()` call to black or white, whichever contrasts
+    /// more with the background, or with an accent as the second argument, to the accent
+    /// darkened or lightened until it is readable on the background. Each argument is either a
+    /// variable such as `$bg` or `colors.$bg`, or a color.
+    fn resolve_contrast_color(
+        name: &str,
+        arguments: &str,
+        variables: &[(String, String)],
+        namespaces: &HashMap<String, Vec<(String, String)>>,
+    ) -> Result<String, String> {
+        let colors = Self::split_arguments(arguments)
+            .into_iter()
+            .map(|argument| Self::resolve_color_argument(name, argument, variables, namespaces))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let contrast_color = match colors.as_slice() {
+            [background] => background.contrast_color(),
+            [background, accent] => background.readable_accent(accent),
+            _ => {
+                return Err(format!(
+                    "${name} calls contrast_color() with {} arguments, expected a background and \
+                     an optional accent",
+                    colors.len()
+                ));
+            }
+        };
+
+        contrast_color.map(String::from).ok_or_else(|| {
+            format!("${name} uses contrast_color() on '{arguments}', which is not a color")
+        })
+    }
+
+    /// Resolves an argument of a `contrast_color()` call, either a variable such as `$bg` or
+    /// `colors.$bg` or a color.
+    fn resolve_color_argument(
+        name: &str,
+        argument: &str,
+        variables: &[(String, String)],
+        namespaces: &HashMap<String, Vec<(String, String)>>,
+    ) -> Result<HexColor, String> {
+        let argument = argument.trim();
+        let color = match VARIABLE_REGEX.captures(argument) {
+            Some(captures) => {
+                let variable_name = &captures[2];
+                let scope = match captures.get(1) {
+                    Some(namespace) => {
+                        Self::namespace_variables(name, namespace.as_str(), namespaces)?
+                    }
+                    None => variables,
+                };
+
+                scope
+                    .iter()
+                    .rev()
+                    .find(|variable| variable.0 == variable_name)
+                    .map(|variable| variable.1.clone())
+                    .ok_or_else(|| format!("${name} uses ${variable_name}, which is not defined"))?
+            }
+            None => argument.to_string(),
+        };
+
+        HexColor::try_from(&color).map_err(|_| {
+            format!("${name} uses contrast_color() on '{color}', which is not a color")
+        })
+    }
+
+    /// Gets the variables of the module used with the given namespace.
+    fn namespace_variables<'a>(
+        name: &str,
+        namespace: &str,
+        namespaces: &'a HashMap<String, Vec<(String, String)>>,
+    ) -> Result<&'a [(String, String)], String> {
+        namespaces
+            .get(namespace)
+            .map(Vec::as_slice)
+            .ok_or_else(|| format!("${name} uses the namespace {namespace}, which is not used"))
+    }
+
+    /// Parses an SCSS map such as `('bg': #1f1f28, 'fg': rgba(220, 215, 186, 1))` into its keys
+    /// and values, or `None` if the value is not a map.
+    fn parse_map(value: &str) -> Option<Vec<(String, String)>> {
+        let entries = value.strip_prefix('(')?.strip_suffix(')')?;
+
+        Self::split_arguments(entries)
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .map(|part| {
+                let (key, value) = part.split_once(':')?;
+                Some((Self::unquote(key).to_string(), value.trim().to_string()))
+            })
+            .collect()
+    }
+
+    /// Splits the arguments of a function call, or the entries of a map, at the commas outside of
+    /// parentheses, which belong to values such as `rgba()`.
+    fn split_arguments(arguments: &str) -> Vec<&str> {
+        let mut depth = 0;
+        let mut start = 0;
+        let mut parts = Vec::new();
+
+        for (index, c) in arguments.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(&arguments[start..index]);
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(&arguments[start..]);
+
+        parts
+    }
+
+    /// Removes the quotes around an SCSS string, if any.
+    fn unquote(value: &str) -> &str {
+        value.trim().trim_matches(|c| c == '\'' || c == '"')
+    }
+
+    /// Splits SCSS into statements at the semicolons outside of strings and parentheses, so that
+    /// a `linear-gradient(...)` spanning several lines or `url("a;b")` is kept whole.
+    fn split_statements(content: &str) -> Vec<&str> {
+        let mut statements = Vec::new();
+        let mut start = 0;
+        let mut depth = 0usize;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+
+        for (index, c) in content.char_indices() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+
+                continue;
+            }
+
+            match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                ';' if depth == 0 => {
+                    statements.push(&content[start..index]);
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+
+        statements.push(&content[start..]);
+        statements
+    }
+
+    /// Removes `/* block */` and `// line` comments from SCSS, so that comments in any position,
+    /// such as `$bg: #1f1f28 /* background */;`, do not end up in variable values. Line breaks
+    /// are kept. Comment markers inside strings and unquoted `url(...)`s are not comments, so that
+    /// `"https://example.com"` and `url(https://example.com/a.png)` survive, and neither is `//`
+    /// directly after a `:`.
+    fn strip_comments(content: &str) -> String {
+        let mut stripped = String::with_capacity(content.len());
+        let mut chars = content.chars().peekable();
+        let mut previous: Option<char> = None;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        let mut in_url = false;
+
+        while let Some(c) = chars.next() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+
+                stripped.push(c);
+                continue;
+            }
+
+            if in_url {
+                in_url = c != ')';
+                stripped.push(c);
+                continue;
+            }
+
+            match (c, chars.peek()) {
+                ('/', Some('*')) => {
+                    chars.next();
+
+                    let mut last = None;
+                    for c in chars.by_ref() {
+                        if c == '\n' {
+                            stripped.push(c);
+                        }
+
+                        if last == Some('*') && c == '/' {
+                            break;
+                        }
+
+                        last = Some(c);
+                    }
+
+                    // Keep the surrounding tokens apart, e.g. `1px/* gap */2px`
+                    stripped.push(' ');
+                }
+                ('/', Some('/')) if previous != Some(':') => {
+                    while chars.next_if(|c| *c != '\n').is_some() {}
+                }
+                ('"' | '\'', _) => {
+                    quote = Some(c);
+                    stripped.push(c);
+                }
+                // A quoted URL is a string, which is handled above
+                ('(', next) if !matches!(next, Some('"' | '\'')) => {
+                    in_url = stripped
+                        .get(stripped.len().saturating_sub(3)..)
+                        .is_some_and(|name| name.eq_ignore_ascii_case("url"));
+                    stripped.push(c);
+                }
+                _ => stripped.push(c),
+            }
+
+            previous = Some(c);
+        }
+
+        stripped
+    }
+
+    /// Reloads the wallpaper by selecting an image from the current theme's wallpaper directory
+    /// and setting it using hyprpaper. The image is selected with the theme's
+    /// [`WallpaperStrategy`], which is random unless set in the theme's metadata.
+    ///
+    /// If videos are enabled in the configuration, videos are selected as well, and played using
+    /// `mpvpaper`, which is stopped again when an image is selected.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The wallpaper directory cannot be read or contains no valid image files.
+    /// - The current theme's metadata cannot be read, or its fixed wallpaper does not exist.
+    /// - hyprctl is not installed, hyprpaper refuses the wallpaper, or hyprpaper is still not
+    ///   running after multiple retry attempts.
+    /// - `mpvpaper` cannot be stopped or started.
+    ///
+    /// Returns [`WallpaperError::TimedOut`] if the wallpaper was not set before the configured
+    /// timeout, which includes commands that hang.
+    pub fn change_wallpaper(settings: &Settings) -> Result<(), WallpaperError> {
+        Self::change_wallpaper_excluding(settings, None).map(|_| ())
+    }
+
+    /// Like [`Self::change_wallpaper()`], but a random wallpaper avoids `excluded` unless it is the
+    /// only image, so that rotating wallpapers never repeats the same one twice in a row.
+    ///
+    /// If wallpapers are set per monitor in the configuration, a wallpaper is selected for each
+    /// monitor, avoiding the one it showed before, and the assignments are recorded in
+    /// [`Paths::wallpaper_assignments_file()`].
+    ///
+    /// Returns the path of the new wallpaper, or of the first monitor's new wallpaper.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same cases as [`Self::change_wallpaper()`].
+    pub fn change_wallpaper_excluding(
+        settings: &Settings,
+        excluded: Option<&Path>,
+    ) -> Result<PathBuf, WallpaperError> {
+        let config = &settings.config;
+        let timeout = Duration::from_secs(
+            config
+                .wallpaper
+                .timeout
+                .unwrap_or(Self::DEFAULT_WALLPAPER_TIMEOUT_SECONDS),
+        );
+        let deadline = Instant::now().checked_add(timeout).ok_or_else(|| {
+            WallpaperError::Failed(format!(
+                "The wallpaper timeout of {} seconds is too long",
+                timeout.as_secs()
+            ))
+        })?;
+
+        let meta = ThemeMetadata::read(&settings.paths.current_theme())?;
+        let wallpaper_dir_path = settings.paths.current_wallpapers();
+
+        // A theme with a solid color instead of wallpapers shows the color on every monitor
+        let solid_color_wallpaper = match meta
+            .as_ref()
+            .and_then(|meta| meta.wallpaper_color.as_deref())
+        {
+            Some(color) if Self::list_wallpapers(settings, &wallpaper_dir_path)?.is_empty() => {
+                Some(Self::write_solid_color_wallpaper(&settings.paths, color)?)
+            }
+            _ => None,
+        };
+
+        let strategy = meta.map(|meta| meta.wallpaper_strategy).unwrap_or_default();
+        let select_wallpaper_file = |excluded: Option<&Path>| match &solid_color_wallpaper {
+            Some(path) => Ok(path.clone()),
+            None => Self::select_wallpaper_file(settings, &strategy, &wallpaper_dir_path, excluded),
+        };
+
+        if config.wallpaper.video {
+            // A playing video covers the wallpaper set by hyprpaper
+            Self::stop_video_wallpaper(timeout, deadline)?;
+        }
+
+        if !config.wallpaper.per_monitor {
+            let wallpaper_file_path = select_wallpaper_file(excluded)?;
+
+            return Self::set_wallpaper(None, wallpaper_file_path, timeout, deadline);
+        }
+
+        let previous_assignments = Self::read_wallpaper_assignments(&settings.paths);
+        let mut assignments: Vec<(String, PathBuf)> = Vec::new();
+
+        for monitor in Self::monitors(timeout, deadline)? {
+            let previous_wallpaper = previous_assignments
+                .iter()
+                .find(|(assigned_monitor, _)| *assigned_monitor == monitor)
+                .map(|(_, path)| path.as_path())
+                .or(excluded);
+
+            let wallpaper_file_path = select_wallpaper_file(previous_wallpaper)?;
+            let wallpaper_file_path =
+                Self::set_wallpaper(Some(&monitor), wallpaper_file_path, timeout, deadline)?;
+
+            assignments.push((monitor, wallpaper_file_path));
+        }
+
+        Self::write_wallpaper_assignments(&settings.paths, &assignments)?;
+
+        assignments
+            .into_iter()
+            .next()
+            .map(|(_, path)| path)
+            .ok_or_else(|| WallpaperError::Failed("No monitors were found".to_string()))
+    }
+
+    /// Writes an image of the solid color to [`Paths::solid_color_wallpapers()`], named after the
+    /// color so that hyprpaper does not show an image it loaded before for another color.
+    fn write_solid_color_wallpaper(paths: &Paths, color: &str) -> Result<PathBuf, String> {
+        let rgb = ThemeMetadata::parse_wallpaper_color(color)?;
+        let directory_path = paths.solid_color_wallpapers();
+        let file_path = directory_path.join(format!("{:02x}{:02x}{:02x}.bmp", rgb.0, rgb.1, rgb.2));
+
+        fs::create_dir_all(&directory_path)
+            .map_err(|e| format!("Could not create {}: {e}", directory_path.display()))?;
+        fs::write(&file_path, Images::solid_color_bmp(rgb))
+            .map_err(|e| format!("Could not write {}: {e}", file_path.display()))?;
+
+        Ok(file_path)
+    }
+
+    /// Selects a wallpaper from the directory with the theme's strategy, where a random wallpaper
+    /// avoids `excluded` if possible.
+    fn select_wallpaper_file(
+        settings: &Settings,
+        strategy: &WallpaperStrategy,
+        wallpaper_dir_path: &Path,
+        excluded: Option<&Path>,
+    ) -> Result<PathBuf, String> {
+        match strategy {
+            WallpaperStrategy::Random => {
+                Self::get_random_wallpaper_file(settings, wallpaper_dir_path, excluded)
+            }
+            WallpaperStrategy::Fixed(file_name) => {
+                Self::get_fixed_wallpaper_file(settings, wallpaper_dir_path, file_name)
+            }
+            WallpaperStrategy::First => {
+                Self::get_sequential_wallpaper_file(settings, wallpaper_dir_path, false)
+            }
+            WallpaperStrategy::Sequential => {
+                Self::get_sequential_wallpaper_file(settings, wallpaper_dir_path, true)
+            }
+        }
+    }
+
+    /// Sets the wallpaper on the monitor, or on every monitor if `None`, playing it with
+    /// `mpvpaper` if it is a video.
+    fn set_wallpaper(
+        monitor: Option<&str>,
+        wallpaper_file_path: PathBuf,
+        timeout: Duration,
+        deadline: Instant,
+    ) -> Result<PathBuf, WallpaperError> {
+        if Images::is_video(&wallpaper_file_path) {
+            return Self::set_video_wallpaper(monitor, wallpaper_file_path)
+                .map_err(WallpaperError::Failed);
+        }
+
+        Self::set_image_wallpaper(monitor, wallpaper_file_path, timeout, deadline)
+    }
+
+    /// Gets the names of the connected monitors, e.g. `DP-1`.
+    fn monitors(timeout: Duration, deadline: Instant) -> Result<Vec<String>, WallpaperError> {
+        let mut command = Command::new("hyprctl");
+        command.arg("monitors").arg("-j");
+
+        let output = Self::output_before(&mut command, timeout, deadline)?;
+        if !output.status.success() {
+            return Err(WallpaperError::Failed(format!(
+                "Could not list monitors: {}",
+                CommandRunner::describe_failure(&command, &output)
+            )));
+        }
+
+        let monitors: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Could not parse the monitors listed by hyprctl: {e}"))?;
+
+        Ok(monitors
+            .iter()
+            .filter_map(|monitor| Some(monitor.get("name")?.as_str()?.to_string()))
+            .collect())
+    }
+
+    /// Reads the wallpaper last set on each monitor, recorded as `monitor = path` lines. Nothing is
+    /// recorded if the file does not exist.
+    fn read_wallpaper_assignments(paths: &Paths) -> Vec<(String, PathBuf)> {
+        let Ok(contents) = fs::read_to_string(paths.wallpaper_assignments_file()) else {
+            return Vec::new();
+        };
+
+        contents
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(monitor, path)| (monitor.trim().to_string(), PathBuf::from(path.trim())))
+            .collect()
+    }
+
+    fn write_wallpaper_assignments(
+        paths: &Paths,
+        assignments: &[(String, PathBuf)],
+    ) -> Result<(), String> {
+        let assignments_file_path = paths.wallpaper_assignments_file();
+        let mut contents = String::new();
+
+        for (monitor, path) in assignments {
+            writeln!(contents, "{monitor} = {}", path.display())
+                .map_err(|e| format!("Could not write line. {e}"))?;
+        }
+
+        fs::write(&assignments_file_path, contents)
+            .map_err(|e| format!("Could not write {}: {e}", assignments_file_path.display()))
+    }
+
+    /// Lists the wallpapers of the current theme that [`Self::change_wallpaper()`] selects from,
+    /// sorted by file name, e.g. for a wallpaper picker.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the current theme's wallpaper directory cannot be read.
+    pub fn current_wallpapers(settings: &Settings) -> Result<Vec<PathBuf>, String> {
+        Self::list_wallpapers(settings, &settings.paths.current_wallpapers())
+    }
+
+    /// Gets the wallpapers hyprpaper is currently showing, as pairs of monitor and wallpaper path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `hyprctl` cannot be executed or reports a failure.
+    pub fn active_wallpapers() -> Result<Vec<(String, PathBuf)>, String> {
+        let output = CommandRunner::run(Command::new("hyprctl").arg("hyprpaper").arg("listactive"))
+            .map_err(|e| format!("Could not list active wallpapers: {e}"))?;
+
+        // Each line is formatted as `monitor = path`
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(monitor, path)| (monitor.trim().to_string(), PathBuf::from(path.trim())))
+            .collect())
+    }
+
+    /// Sets the image with hyprpaper, retrying while hyprpaper is not ready until it succeeds, the
+    /// attempts run out, or the `deadline` of the whole wallpaper change passes. Other failures,
+    /// such as a wrong path, are returned right away.
+    fn set_image_wallpaper(
+        monitor: Option<&str>,
+        wallpaper_file_path: PathBuf,
+        timeout: Duration,
+        deadline: Instant,
+    ) -> Result<PathBuf, WallpaperError> {
+        let max_attempts = 5;
+        let mut error: Option<WallpaperError> = None;
+
+        if !wallpaper_file_path.is_file() {
+            return Err(WallpaperError::Rejected(format!(
+                "{} is not a file",
+                wallpaper_file_path.display()
+            )));
+        }
+
+        let wallpaper_arg = format!(
+            "{},{}",
+            monitor.unwrap_or_default(),
+            wallpaper_file_path.display()
+        );
+
+        for _ in 1..=max_attempts {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(WallpaperError::TimedOut(timeout));
+            }
+
+            let output = CommandRunner::output_with_timeout(
+                Command::new("hyprctl")
+                    .arg("hyprpaper")
+                    .arg("wallpaper")
+                    .arg(&wallpaper_arg),
+                remaining.min(Self::HYPRCTL_TIMEOUT),
+            );
+
+            match Self::classify_hyprpaper_result(output) {
+                Ok(()) => return Ok(wallpaper_file_path),
+                Err(e @ WallpaperError::NotReady(_)) => error = Some(e),
+                Err(e) => return Err(e),
+            }
+
+            thread::sleep(
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .min(Duration::from_secs(1)),
+            );
+        }
+
+        if Instant::now() >= deadline {
+            return Err(WallpaperError::TimedOut(timeout));
+        }
+
+        Err(error.unwrap_or_else(|| WallpaperError::Failed("Unknown error".to_string())))
+    }
+
+    /// Tells apart the ways `hyprctl hyprpaper wallpaper` can fail: hyprctl not being installed,
+    /// hyprpaper not being reachable yet, hyprpaper answering with an error, and hyprctl failing
+    /// without an answer.
+    fn classify_hyprpaper_result(output: Result<Output, Error>) -> Result<(), WallpaperError> {
+        let result = match output {
+            Ok(result) => result,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(WallpaperError::ProgramMissing(format!("hyprctl: {e}")));
+            }
+            // A hanging hyprctl is retried like an unreachable hyprpaper
+            Err(e) if e.kind() == ErrorKind::TimedOut => {
+                return Err(WallpaperError::NotReady(format!("hyprctl: {e}")));
+            }
+            Err(e) => return Err(WallpaperError::UnexpectedResponse(format!("hyprctl: {e}"))),
+        };
+
+        let response = String::from_utf8_lossy(&result.stdout).trim().to_string();
+        let stderr = String::from_utf8_lossy(&result.stderr).trim().to_string();
+
+        if result.status.success() && response.is_empty() {
+            return Ok(());
+        }
+
+        // hyprctl answers for hyprpaper when it cannot reach its socket, e.g. "Couldn't connect
+        // to /run/user/1000/hypr/.../.hyprpaper.sock. (3)"
+        let message = if response.is_empty() {
+            &stderr
+        } else {
+            &response
+        };
+        if message.contains("connect") {
+            return Err(WallpaperError::NotReady(message.clone()));
+        }
+
+        if response.is_empty() {
+            return Err(WallpaperError::UnexpectedResponse(format!(
+                "hyprctl failed with {}: {stderr}",
+                result.status
+            )));
+        }
+
+        Err(WallpaperError::Rejected(response))
+    }
+
+    fn set_video_wallpaper(
+        monitor: Option<&str>,
+        wallpaper_file_path: PathBuf,
+    ) -> Result<PathBuf, String> {
+        CommandRunner::spawn(
+            Command::new("nohup")
+                .arg("mpvpaper")
+                .arg("--mpv-options")
+                .arg("no-audio loop")
+                .arg(monitor.unwrap_or("*"))
+                .arg(&wallpaper_file_path)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null()),
+        )
+        .map_err(|e| format!("Failed to start mpvpaper: {e}"))?;
+
+        Ok(wallpaper_file_path)
+    }
+
+    fn stop_video_wallpaper(timeout: Duration, deadline: Instant) -> Result<(), WallpaperError> {
+        let mut pkill = Command::new("pkill");
+        pkill.arg("-x").arg("mpvpaper");
+
+        let output = Self::output_before(&mut pkill, timeout, deadline)?;
+
+        // pkill exits with 1 when no process matched, i.e. no video was playing
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            return Err(WallpaperError::Failed(format!(
+                "Failed to stop mpvpaper: {}",
+                CommandRunner::describe_failure(&pkill, &output)
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Runs a command that is part of changing the wallpaper, killing it if it has not exited
+    /// before the `deadline` of the whole wallpaper change.
+    fn output_before(
+        command: &mut Command,
+        timeout: Duration,
+        deadline: Instant,
+    ) -> Result<Output, WallpaperError> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(WallpaperError::TimedOut(timeout));
+        }
+
+        CommandRunner::output_with_timeout(command, remaining).map_err(|e| {
+            if e.kind() == ErrorKind::TimedOut {
+                WallpaperError::TimedOut(timeout)
+            } else {
+                WallpaperError::Failed(format!(
+                    "Could not execute {}: {e}",
+                    command.get_program().to_string_lossy()
+                ))
+            }
+        })
+    }
+
+    /// Lists the wallpapers in the directory, which are the images, and the videos if they are
+    /// enabled in the configuration, sorted by path.
+    fn list_wallpapers(settings: &Settings, path: &Path) -> Result<Vec<PathBuf>, String> {
+        // A theme without a wallpaper directory has no wallpapers, while a broken symbolic link
+        // is an error below
+        if fs::symlink_metadata(path).is_err_and(|e| e.kind() == ErrorKind::NotFound) {
+            return Ok(Vec::new());
+        }
+
+        Self::ensure_within_themes_dir(&settings.paths, path)?;
+
+        let mut wallpaper_files = Images::list(path)?;
+
+        if settings.config.wallpaper.video {
+            wallpaper_files.extend(Images::list_videos(path)?);
+            wallpaper_files.sort();
+        }
+
+        Ok(wallpaper_files)
+    }
+
+    /// Checks that the path, with its symbolic links resolved, is inside the theme directory, so
+    /// that a misconfigured theme cannot make `current` point at, or the wallpapers
+    /// be read from, anywhere else. A copy of the current theme made with `--copy-current` lives
+    /// in the state directory instead and is allowed as well.
+    fn ensure_within_themes_dir(paths: &Paths, path: &Path) -> Result<(), String> {
+        let config_path = paths.config_path();
+        let themes_dir_path = fs::canonicalize(config_path)
+            .map_err(|e| format!("Could not resolve {}: {e}", config_path.display()))?;
+        let resolved_path = fs::canonicalize(path)
+            .map_err(|e| format!("Could not resolve {}: {e}", path.display()))?;
+        let current_theme_path = paths.current_theme();
+        let is_in_copied_current_theme = !current_theme_path.is_symlink()
+            && fs::canonicalize(&current_theme_path)
+                .is_ok_and(|current_theme_path| resolved_path.starts_with(current_theme_path));
+
+        if !resolved_path.starts_with(&themes_dir_path) && !is_in_copied_current_theme {
+            return Err(format!(
+                "{} is outside of the theme directory {}",
+                resolved_path.display(),
+                themes_dir_path.display()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the wallpaper with the given file name, which must be one of the theme's wallpapers.
+    fn get_fixed_wallpaper_file(
+        settings: &Settings,
+        path: &Path,
+        file_name: &str,
+    ) -> Result<PathBuf, String> {
+        let wallpaper_file_path = path.join(file_name);
+
+        if !Self::list_wallpapers(settings, path)?.contains(&wallpaper_file_path) {
+            return Err(format!(
+                "The fixed wallpaper '{file_name}' was not found in {}",
+                path.display()
+            ));
+        }
+
+        Ok(wallpaper_file_path)
+    }
+
+    /// Gets the first wallpaper sorted by file name, or if `advance` is set, the wallpaper after
+    /// the one selected last time for the current theme, starting over after the last one. The
+    /// index of the selected wallpaper is recorded in [`Paths::wallpaper_index_file()`].
+    fn get_sequential_wallpaper_file(
+        settings: &Settings,
+        path: &Path,
+        advance: bool,
+    ) -> Result<PathBuf, String> {
+        let wallpaper_files = Self::list_wallpapers(settings, path)?;
+
+        if wallpaper_files.is_empty() {
+            return Err("No image files found in theme directory".to_string());
+        }
+
+        let index = if advance {
+            let theme_dir_name = Self::current_theme_directory(settings)
+                .and_then(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| "current".to_string());
+            let index_file_path = settings.paths.wallpaper_index_file(&theme_dir_name);
+            let previous_index = fs::read_to_string(&index_file_path)
+                .ok()
+                .and_then(|contents| contents.trim().parse::<usize>().ok());
+            // The file can be edited by hand, so the index is not trusted to be in range
+            let index = previous_index.map_or(0, |previous_index| {
+                previous_index.wrapping_add(1) % wallpaper_files.len()
+            });
+
+            fs::create_dir_all(index_file_path.parent().unwrap()).map_err(|e| {
+                format!(
+                    "Could not create parent directories for {}. {e}",
+                    index_file_path.display()
+                )
+            })?;
+            fs::write(&index_file_path, index.to_string())
+                .map_err(|e| format!("Could not write {}: {e}", index_file_path.display()))?;
+
+            index
+        } else {
+            0
+        };
+
+        Ok(wallpaper_files[index].clone())
+    }
+
+    fn get_random_wallpaper_file(
+        settings: &Settings,
+        path: &Path,
+        excluded: Option<&Path>,
+    ) -> Result<PathBuf, String> {
+        let wallpaper_files = Self::list_wallpapers(settings, path)?;
+
+        if wallpaper_files.is_empty() {
+            return Err("No image files found in theme directory".to_string());
+        }
+
+        let candidates: Vec<&PathBuf> = wallpaper_files
+            .iter()
+            .filter(|path| Some(path.as_path()) != excluded)
+            .collect();
+
+        let candidates = if candidates.is_empty() {
+            wallpaper_files.iter().collect()
+        } else {
+            candidates
+        };
 
         // Select a random image
         let mut rng = rand::rng();
 
-        image_files
+        candidates
             .choose(&mut rng)
+            .copied()
             .cloned()
             .ok_or_else(|| "Failed to select random image".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+    use crate::models::theme::ColorScheme;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn variables(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    fn interpolate(value: &str, variables: &[(String, String)]) -> Result<String, String> {
+        ThemeService::interpolate("termFont", value, variables, &HashMap::new())
+    }
+
+    #[test]
+    fn interpolate_mixes_literal_text_and_variables() {
+        let variables = variables(&[("font", "\"JetBrains Mono\"")]);
+
+        assert_eq!(
+            interpolate("\"#{$font} Nerd Font\"", &variables),
+            Ok("\"JetBrains Mono Nerd Font\"".to_string())
+        );
+    }
+
+    #[test]
+    fn interpolate_replaces_every_interpolation() {
+        let variables = variables(&[("family", "'Fira'"), ("style", "Bold"), ("size", "12")]);
+
+        assert_eq!(
+            interpolate("\"#{$family} #{$style} #{ $size }px\"", &variables),
+            Ok("\"Fira Bold 12px\"".to_string())
+        );
+    }
+
+    #[test]
+    fn interpolate_uses_the_last_definition() {
+        let variables = variables(&[("font", "Fira"), ("font", "Hack")]);
+
+        assert_eq!(interpolate("#{$font}", &variables), Ok("Hack".to_string()));
+    }
+
+    #[test]
+    fn interpolate_resolves_module_variables() {
+        let namespaces = HashMap::from([("fonts".to_string(), variables(&[("mono", "Hack")]))]);
+
+        assert_eq!(
+            ThemeService::interpolate("termFont", "#{fonts.$mono} Nerd Font", &[], &namespaces),
+            Ok("Hack Nerd Font".to_string())
+        );
+    }
+
+    #[test]
+    fn interpolate_fails_for_undefined_variables() {
+        let error = interpolate("\"#{$font} Nerd Font\"", &[]).unwrap_err();
+
+        assert!(error.contains("$font"), "{error}");
+    }
+
+    #[test]
+    fn interpolate_fails_for_expressions() {
+        assert!(interpolate("#{1 + 2}", &[]).is_err());
+    }
+
+    #[test]
+    fn validate_theme_name_accepts_directory_names() {
+        for name in ["kanagawa", "Rosé Pine", "nord.dark", "v1.2"] {
+            assert_eq!(ThemeService::validate_theme_name(name), Ok(()), "{name}");
+        }
+    }
+
+    #[test]
+    fn validate_theme_name_rejects_paths() {
+        for name in [
+            "",
+            "  ",
+            "../kanagawa",
+            "..",
+            "themes/kanagawa",
+            "a\\b",
+            "/tmp",
+        ] {
+            assert!(ThemeService::validate_theme_name(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn strip_comments_removes_block_and_line_comments() {
+        assert_eq!(
+            ThemeService::strip_comments(
+                "$bg: #1f1f28 /* background */;\n// Colors\n$fg: #fff; // text"
+            ),
+            "$bg: #1f1f28  ;\n\n$fg: #fff; "
+        );
+    }
+
+    #[test]
+    fn strip_comments_keeps_line_breaks_of_block_comments() {
+        assert_eq!(
+            ThemeService::strip_comments("$a: 1;/* one\ntwo */$b: 2;"),
+            "$a: 1;\n $b: 2;"
+        );
+    }
+
+    #[test]
+    fn strip_comments_keeps_comment_markers_in_strings() {
+        for content in [
+            "$font: \"Fira // Code\";",
+            "$link: 'https://example.com/*';",
+            "$image: url(\"https://example.com/a.png\");",
+            "$escaped: \"a \\\" // b\";",
+        ] {
+            assert_eq!(ThemeService::strip_comments(content), content);
+        }
+    }
+
+    #[test]
+    fn strip_comments_keeps_comment_markers_in_urls() {
+        assert_eq!(
+            ThemeService::strip_comments("$image: URL(https://example.com/a.png); // wallpaper"),
+            "$image: URL(https://example.com/a.png); "
+        );
+        assert_eq!(
+            ThemeService::strip_comments("$size: calc(1px //2px\n);"),
+            "$size: calc(1px \n);"
+        );
+    }
+
+    #[test]
+    fn collect_variables_fails_for_use_cycles() {
+        let directory = TempDir::new().unwrap();
+        fs::write(directory.path().join("a.scss"), "@use \"b\";\n$a: #fff;\n").unwrap();
+        fs::write(directory.path().join("b.scss"), "@use \"a\";\n$b: #000;\n").unwrap();
+
+        let error = ThemeService::collect_variables(&directory.path().join("a.scss")).unwrap_err();
+
+        assert!(error.contains("uses itself"), "{error}");
+    }
+
+    #[test]
+    fn collect_variables_resolves_partials_used_by_several_files() {
+        let directory = TempDir::new().unwrap();
+        fs::write(directory.path().join("base.scss"), "$bg: #000;\n").unwrap();
+        fs::write(
+            directory.path().join("colors.scss"),
+            "@use \"base\" as *;\n$fg: #fff;\n",
+        )
+        .unwrap();
+        fs::write(
+            directory.path().join("theme.scss"),
+            "@use \"base\";\n@use \"colors\" as *;\n$accent: base.$bg;\n",
+        )
+        .unwrap();
+
+        let variables =
+            ThemeService::collect_variables(&directory.path().join("theme.scss")).unwrap();
+
+        assert_eq!(
+            variables.last(),
+            Some(&("accent".to_string(), "#000".to_string()))
+        );
+    }
+
+    #[test]
+    fn collect_variables_reads_declarations_spanning_several_lines() {
+        let directory = TempDir::new().unwrap();
+        fs::write(
+            directory.path().join("theme.scss"),
+            "$gradient: linear-gradient(\n  #fff,\n  #000\n);\n$bg: #111;\n",
+        )
+        .unwrap();
+
+        let variables =
+            ThemeService::collect_variables(&directory.path().join("theme.scss")).unwrap();
+
+        assert_eq!(
+            variables,
+            vec![
+                (
+                    "gradient".to_string(),
+                    "linear-gradient(#fff,#000)".to_string()
+                ),
+                ("bg".to_string(), "#111".to_string()),
+            ]
+        );
+    }
+
+    fn collect_theme_variables(theme: &str) -> Vec<(String, String)> {
+        let directory = TempDir::new().unwrap();
+        fs::create_dir(directory.path().join("shared")).unwrap();
+        fs::write(
+            directory.path().join("shared/palette.scss"),
+            "$base: #1e1e2e;\n$colors: (base: #1e1e2e);\n",
+        )
+        .unwrap();
+        fs::write(directory.path().join("shared/theme.scss"), theme).unwrap();
+
+        ThemeService::collect_variables(&directory.path().join("shared/theme.scss")).unwrap()
+    }
+
+    #[test]
+    fn collect_variables_resolves_namespaces_given_with_as() {
+        let variables = collect_theme_variables("@use \"palette\" as p;\n$bg: p.$base;\n");
+
+        assert_eq!(
+            variables.last(),
+            Some(&("bg".to_string(), "#1e1e2e".to_string()))
+        );
+    }
+
+    #[test]
+    fn collect_variables_resolves_variables_used_without_a_namespace() {
+        let variables =
+            collect_theme_variables("@use \"palette\" as *;\n$bg: map.get($colors, base);\n");
+
+        assert_eq!(
+            variables.last(),
+            Some(&("bg".to_string(), "#1e1e2e".to_string()))
+        );
+    }
+
+    #[test]
+    fn collect_variables_names_the_namespace_after_the_file() {
+        let variables =
+            collect_theme_variables("@use \"../shared/palette\";\n$bg: palette.$base;\n");
+
+        assert_eq!(
+            variables.last(),
+            Some(&("bg".to_string(), "#1e1e2e".to_string()))
+        );
+    }
+
+    #[test]
+    fn collect_variables_does_not_resolve_a_renamed_namespace_by_the_file_name() {
+        let directory = TempDir::new().unwrap();
+        fs::write(directory.path().join("palette.scss"), "$base: #1e1e2e;\n").unwrap();
+        fs::write(
+            directory.path().join("theme.scss"),
+            "@use \"palette\" as p;\n$bg: palette.$base;\n",
+        )
+        .unwrap();
+
+        let error =
+            ThemeService::collect_variables(&directory.path().join("theme.scss")).unwrap_err();
+
+        assert!(error.contains("palette"), "{error}");
+    }
+
+    fn resolve_value(value: &str) -> Result<String, String> {
+        ThemeService::resolve_value("accent", value, &[], &HashMap::new())
+    }
+
+    #[test]
+    fn resolve_value_picks_a_contrast_color() {
+        let variables = [("bg".to_string(), "#eff1f5".to_string())];
+
+        assert_eq!(
+            ThemeService::resolve_value("fg", "contrast_color($bg)", &variables, &HashMap::new()),
+            Ok("#000000".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_value_darkens_an_accent_until_it_is_readable() {
+        let variables = [
+            ("bg".to_string(), "#eff1f5".to_string()),
+            ("pink".to_string(), "#f5c2e7".to_string()),
+        ];
+
+        let accent = ThemeService::resolve_value(
+            "fg",
+            "contrast_color($bg,$pink)",
+            &variables,
+            &HashMap::new(),
+        )
+        .unwrap();
+        let background = HexColor::try_from("#eff1f5").unwrap();
+
+        assert_ne!(accent, "#F5C2E7");
+        assert!(
+            background
+                .contrast_ratio(&HexColor::try_from(&accent).unwrap())
+                .unwrap()
+                >= HexColor::MIN_TEXT_CONTRAST
+        );
+    }
+
+    #[test]
+    fn resolve_value_fails_for_contrast_colors_of_non_colors() {
+        assert!(resolve_value("contrast_color(10px)").is_err());
+        assert!(resolve_value("contrast_color(#000,#fff,#000)").is_err());
+    }
+
+    #[test]
+    fn resolve_value_keeps_css_functions() {
+        for value in [
+            "linear-gradient(45deg,rgba(1,2,3,1) 0%,#fff 100%)",
+            "var(--accent,#fff)",
+            "min(10px,2vw)",
+            "rgba(1,2,3,0.5)",
+        ] {
+            assert_eq!(resolve_value(value), Ok(value.to_string()));
+        }
+    }
+
+    #[test]
+    fn resolve_value_fails_for_sass_functions() {
+        for value in [
+            "darken(#fff,10%)",
+            "rgba(lighten(#000,5%),0.5)",
+            "color.adjust(#fff,$lightness:-10%)",
+        ] {
+            assert!(resolve_value(value).is_err(), "{value}");
+        }
+    }
+
+    #[test]
+    fn normalize_unquoted_whitespace_keeps_whitespace_in_strings() {
+        assert_eq!(
+            ThemeService::normalize_unquoted_whitespace("rgba(1, 2, 3, 1)"),
+            "rgba(1,2,3,1)"
+        );
+        assert_eq!(
+            ThemeService::normalize_unquoted_whitespace("\"JetBrains Mono\" , 'Nerd  Font'"),
+            "\"JetBrains Mono\",'Nerd  Font'"
+        );
+        assert_eq!(
+            ThemeService::normalize_unquoted_whitespace(r#""a \" b"  c"#),
+            r#""a \" b" c"#
+        );
+    }
+
+    #[test]
+    fn normalize_unquoted_whitespace_keeps_the_parts_of_values_apart() {
+        assert_eq!(
+            ThemeService::normalize_unquoted_whitespace(
+                "linear-gradient(\n  45deg,\n  rgba(1, 2, 3, 1) 0%,\n  #fff   100%\n)"
+            ),
+            "linear-gradient(45deg,rgba(1,2,3,1) 0%,#fff 100%)"
+        );
+        assert_eq!(
+            ThemeService::normalize_unquoted_whitespace("1px  solid #fff"),
+            "1px solid #fff"
+        );
+    }
+
+    #[test]
+    fn rename_metadata_keeps_the_comments_and_order_of_meta_toml() {
+        let directory = TempDir::new().unwrap();
+        fs::write(
+            directory.path().join("meta.toml"),
+            "# A warm variant\nname = \"Kanagawa\" # shown in the list\ncolor_scheme = \"Dark\"\n",
+        )
+        .unwrap();
+
+        ThemeService::rename_metadata(directory.path(), "kanagawa-warm").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(directory.path().join("meta.toml")).unwrap(),
+            "# A warm variant\nname = \"kanagawa-warm\" # shown in the list\ncolor_scheme = \"Dark\"\n"
+        );
+    }
+
+    #[test]
+    fn rename_metadata_adds_a_missing_name() {
+        let directory = TempDir::new().unwrap();
+        fs::write(
+            directory.path().join("meta.toml"),
+            "color_scheme = \"Dark\"\n\n[extra]\nkey = 1\n",
+        )
+        .unwrap();
+
+        ThemeService::rename_metadata(directory.path(), "kanagawa-warm").unwrap();
+
+        let contents = fs::read_to_string(directory.path().join("meta.toml")).unwrap();
+        let document: toml::Table = toml::from_str(&contents).unwrap();
+        assert_eq!(document["name"].as_str(), Some("kanagawa-warm"));
+        assert!(contents.starts_with("color_scheme = \"Dark\"\n"));
+    }
+
+    fn current_theme_settings(themes_dir: &TempDir) -> (Settings, Theme) {
+        let theme_path = themes_dir.path().join("kanagawa");
+        fs::create_dir(&theme_path).unwrap();
+        fs::write(
+            theme_path.join(Theme::DEFAULT_VARIABLES_FILE_NAME),
+            "$bg: #000;\n",
+        )
+        .unwrap();
+
+        let settings = Settings {
+            paths: Paths::new(
+                themes_dir.path().to_path_buf(),
+                themes_dir.path().to_path_buf(),
+            ),
+            config: Config::default(),
+        };
+        let theme = Theme::new(
+            "Kanagawa",
+            "",
+            theme_path,
+            None,
+            ColorScheme::Dark,
+            "",
+            Theme::DEFAULT_VARIABLES_FILE_NAME,
+        );
+
+        (settings, theme)
+    }
+
+    fn has_wallpaper_issue(issues: &[ThemeIssue]) -> bool {
+        issues.iter().any(|issue| {
+            matches!(
+                issue,
+                ThemeIssue::NoWallpapers(_) | ThemeIssue::InvalidWallpapers(_)
+            )
+        })
+    }
+
+    #[test]
+    fn find_issues_only_reports_missing_wallpapers_when_the_wallpaper_is_set() {
+        let themes_dir = TempDir::new().unwrap();
+        let (settings, theme) = current_theme_settings(&themes_dir);
+
+        assert!(has_wallpaper_issue(&ThemeService::find_issues(
+            &settings,
+            &theme,
+            &ApplyOptions::default()
+        )));
+
+        for options in [
+            ApplyOptions {
+                components: vec![Component::Kitty],
+                ..ApplyOptions::default()
+            },
+            ApplyOptions {
+                no_reload: true,
+                ..ApplyOptions::default()
+            },
+        ] {
+            assert!(
+                !has_wallpaper_issue(&ThemeService::find_issues(&settings, &theme, &options)),
+                "{options:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn find_issues_reports_thumbnails_that_are_not_png_files() {
+        let themes_dir = TempDir::new().unwrap();
+        let (settings, mut theme) = current_theme_settings(&themes_dir);
+        let options = ApplyOptions {
+            components: vec![Component::Kitty],
+            ..ApplyOptions::default()
+        };
+
+        theme.thumbnail_path = Some(theme.directory_path.join("thumbnail.jpg"));
+        assert!(matches!(
+            ThemeService::find_issues(&settings, &theme, &options)[..],
+            [ThemeIssue::UnsupportedThumbnail(_)]
+        ));
+
+        theme.thumbnail_path = Some(theme.directory_path.join("thumbnail.PNG"));
+        assert!(ThemeService::find_issues(&settings, &theme, &options).is_empty());
+    }
+
+    #[test]
+    fn set_current_theme_is_partial_when_every_step_fails_after_switching_current() {
+        let themes_dir = TempDir::new().unwrap();
+        let (mut settings, theme) = current_theme_settings(&themes_dir);
+        // Kitty's `theme-template.conf` does not exist, so applying the theme to Kitty fails
+        settings.config.paths.kitty = Some(themes_dir.path().join("kitty").display().to_string());
+        let options = ApplyOptions {
+            components: vec![Component::Kitty],
+            no_reload: true,
+            ..ApplyOptions::default()
+        };
+
+        let result = ThemeService::set_current_theme(&settings, &theme, &options);
+
+        assert!(matches!(result, Err(ApplyError::Partial(_))), "{result:?}");
+        assert_eq!(
+            ThemeService::current_theme_directory(&settings),
+            Some(theme.directory_path)
+        );
+    }
+
+    #[test]
+    fn update_current_theme_replaces_a_copy_with_a_symlink() {
+        let themes_dir = TempDir::new().unwrap();
+        let (settings, theme) = current_theme_settings(&themes_dir);
+
+        ThemeService::update_current_theme(&settings, &theme, true).unwrap();
+        assert!(!settings.paths.current_theme().is_symlink());
+
+        ThemeService::update_current_theme(&settings, &theme, false).unwrap();
+        assert_eq!(
+            fs::read_link(settings.paths.current_theme()).unwrap(),
+            theme.directory_path
+        );
+    }
+
+    #[test]
+    fn update_current_theme_swaps_in_a_new_copy_without_leaving_temporary_directories() {
+        let themes_dir = TempDir::new().unwrap();
+        let (settings, theme) = current_theme_settings(&themes_dir);
+        let other_theme_path = themes_dir.path().join("nord");
+        fs::create_dir(&other_theme_path).unwrap();
+        fs::write(other_theme_path.join("notes.md"), "nord").unwrap();
+        let other_theme = Theme::new(
+            "Nord",
+            "",
+            other_theme_path.clone(),
+            None,
+            ColorScheme::Dark,
+            "",
+            Theme::DEFAULT_VARIABLES_FILE_NAME,
+        );
+
+        ThemeService::update_current_theme(&settings, &theme, false).unwrap();
+        ThemeService::update_current_theme(&settings, &theme, true).unwrap();
+        ThemeService::update_current_theme(&settings, &other_theme, true).unwrap();
+
+        assert_eq!(
+            ThemeService::current_theme_directory(&settings),
+            Some(other_theme_path)
+        );
+        assert!(settings.paths.current_theme().join("notes.md").is_file());
+        assert!(!fs::read_dir(themes_dir.path()).unwrap().any(|entry| {
+            entry
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(".current")
+        }));
+    }
+
+    #[test]
+    fn list_wallpapers_reads_a_copied_current_theme_in_the_state_directory() {
+        let themes_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        let (mut settings, theme) = current_theme_settings(&themes_dir);
+        settings.paths = Paths::new(
+            themes_dir.path().to_path_buf(),
+            state_dir.path().to_path_buf(),
+        );
+        let wallpapers_path = theme.directory_path.join("wallpapers");
+        fs::create_dir(&wallpapers_path).unwrap();
+        fs::write(wallpapers_path.join("forest.png"), "").unwrap();
+
+        ThemeService::update_current_theme(&settings, &theme, true).unwrap();
+        let current_wallpapers_path = settings.paths.current_wallpapers();
+
+        assert_eq!(
+            ThemeService::list_wallpapers(&settings, &current_wallpapers_path).unwrap(),
+            vec![current_wallpapers_path.join("forest.png")]
+        );
+    }
+
+    #[test]
+    fn get_sequential_wallpaper_file_advances_per_theme_and_wraps_any_recorded_index() {
+        let themes_dir = TempDir::new().unwrap();
+        let (settings, theme) = current_theme_settings(&themes_dir);
+        let wallpapers_path = theme.directory_path.join("wallpapers");
+        fs::create_dir(&wallpapers_path).unwrap();
+        fs::write(wallpapers_path.join("forest.png"), "").unwrap();
+        fs::write(wallpapers_path.join("lake.png"), "").unwrap();
+        ThemeService::update_current_theme(&settings, &theme, false).unwrap();
+        let current_wallpapers_path = settings.paths.current_wallpapers();
+
+        // Another theme's position is not continued
+        fs::create_dir_all(
+            settings
+                .paths
+                .wallpaper_index_file("nord")
+                .parent()
+                .unwrap(),
+        )
+        .unwrap();
+        fs::write(settings.paths.wallpaper_index_file("nord"), "0").unwrap();
+
+        let first =
+            ThemeService::get_sequential_wallpaper_file(&settings, &current_wallpapers_path, true)
+                .unwrap();
+        assert_eq!(first, current_wallpapers_path.join("forest.png"));
+
+        fs::write(
+            settings.paths.wallpaper_index_file("kanagawa"),
+            usize::MAX.to_string(),
+        )
+        .unwrap();
+
+        let wrapped =
+            ThemeService::get_sequential_wallpaper_file(&settings, &current_wallpapers_path, true)
+                .unwrap();
+        assert_eq!(wrapped, current_wallpapers_path.join("forest.png"));
+    }
+
+    #[test]
+    fn list_wallpapers_refuses_directories_outside_the_theme_directory() {
+        let themes_dir = TempDir::new().unwrap();
+        let other_dir = TempDir::new().unwrap();
+        let (settings, _) = current_theme_settings(&themes_dir);
+        fs::write(other_dir.path().join("forest.png"), "").unwrap();
+
+        assert!(ThemeService::list_wallpapers(&settings, other_dir.path()).is_err());
+    }
+
+    #[test]
+    fn update_current_theme_leaves_directories_it_did_not_copy_alone() {
+        let themes_dir = TempDir::new().unwrap();
+        let (settings, theme) = current_theme_settings(&themes_dir);
+        let current_theme_path = settings.paths.current_theme();
+        fs::create_dir(&current_theme_path).unwrap();
+        fs::write(current_theme_path.join("notes.md"), "mine").unwrap();
+
+        assert!(ThemeService::update_current_theme(&settings, &theme, false).is_err());
+        assert!(ThemeService::update_current_theme(&settings, &theme, true).is_err());
+        assert!(current_theme_path.join("notes.md").is_file());
+    }
+
+    #[test]
+    fn output_dir_leaves_the_current_theme_and_hooks_alone() {
+        let themes_dir = TempDir::new().unwrap();
+        let state_dir = TempDir::new().unwrap();
+        let output_dir = TempDir::new().unwrap();
+
+        let theme_path = themes_dir.path().join("kanagawa");
+        fs::create_dir(&theme_path).unwrap();
+        fs::write(
+            theme_path.join(Theme::DEFAULT_VARIABLES_FILE_NAME),
+            "$backgroundColor: #1f1f28;\n",
+        )
+        .unwrap();
+
+        // Fails applying the theme if it is run
+        let hook_path = theme_path.join(Hook::PreApply.file_name());
+        fs::write(&hook_path, "#!/bin/sh\nexit 1\n").unwrap();
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let settings = Settings {
+            paths: Paths::new(
+                themes_dir.path().to_path_buf(),
+                state_dir.path().to_path_buf(),
+            ),
+            config: Config::default(),
+        };
+        let theme = Theme::new(
+            "Kanagawa",
+            "",
+            theme_path,
+            None,
+            ColorScheme::Dark,
+            "",
+            Theme::DEFAULT_VARIABLES_FILE_NAME,
+        );
+        let options = ApplyOptions {
+            components: vec![Component::Btop],
+            output_dir: Some(output_dir.path().to_path_buf()),
+            ..ApplyOptions::default()
+        };
+
+        ThemeService::set_current_theme(&settings, &theme, &options).unwrap();
+
+        assert!(!settings.paths.current_theme().exists());
+        assert!(output_dir.path().join(".config/btop/btop.conf").is_file());
+    }
+}
@@ -1,11 +1,21 @@
+use crate::models::component::Component;
 use crate::services::themers::{ThemeContext, Themer};
 use crate::utils::paths::Paths;
 use regex::Regex;
-use std::fs;
+use std::io;
+use std::sync::LazyLock;
+
+/// Matches the `color_theme` line of `btop.conf`.
+static COLOR_THEME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?m)^color_theme = ".*"$"#).unwrap());
 
 pub struct BtopThemer;
 
 impl Themer for BtopThemer {
+    fn component(&self) -> Component {
+        Component::Btop
+    }
+
     fn apply(&self, context: &ThemeContext<'_>) -> Result<(), String> {
         let color_theme = match context.theme.btop_theme_path.as_deref() {
             Some(path) => path.display().to_string(),
@@ -13,40 +23,95 @@ impl Themer for BtopThemer {
         };
 
         let btop_conf_path = Paths::user_home()?.join(".config/btop/btop.conf");
+        let destination_path = context.destination(&btop_conf_path)?;
         let theme_line = format!(r#"color_theme = "{color_theme}""#);
 
-        if btop_conf_path.exists() {
-            let Ok(btop_conf) = fs::read_to_string(&btop_conf_path) else {
-                return Err(format!("Could not read: {}", btop_conf_path.display()));
-            };
-
-            let color_theme_regex =
-                Regex::new(r#"(?m)^color_theme = ".*"$"#).map_err(|e| e.to_string())?;
-
-            let updated_content = if color_theme_regex.is_match(&btop_conf) {
-                color_theme_regex
-                    .replace_all(&btop_conf, theme_line.as_str())
-                    .to_string()
-            } else {
-                let mut new_content = btop_conf;
-                new_content.push('\n');
-                new_content.push_str(&theme_line);
-                new_content.push('\n');
-                new_content
-            };
-
-            fs::write(&btop_conf_path, updated_content)
-                .map_err(|e| format!("Could not write: {} ({e})", btop_conf_path.display()))?;
-        } else {
-            if let Some(parent) = btop_conf_path.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Could not create config directory: {e}"))?;
+        let updated_content = match context.file_system.read_to_string(&btop_conf_path) {
+            Ok(btop_conf) => {
+                if COLOR_THEME_REGEX.is_match(&btop_conf) {
+                    COLOR_THEME_REGEX
+                        .replace_all(&btop_conf, theme_line.as_str())
+                        .to_string()
+                } else {
+                    let mut new_content = btop_conf;
+                    new_content.push('\n');
+                    new_content.push_str(&theme_line);
+                    new_content.push('\n');
+                    new_content
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => format!("{theme_line}\n"),
+            Err(e) => {
+                return Err(format!(
+                    "Could not read: {} ({e})",
+                    btop_conf_path.display()
+                ));
             }
+        };
 
-            fs::write(&btop_conf_path, format!("{theme_line}\n"))
-                .map_err(|e| format!("Could not write: {} ({e})", btop_conf_path.display()))?;
+        if let Some(parent) = destination_path.parent() {
+            context
+                .file_system
+                .create_dir_all(parent)
+                .map_err(|e| format!("Could not create config directory: {e}"))?;
         }
 
+        context
+            .file_system
+            .write(&destination_path, &updated_content)
+            .map_err(|e| format!("Could not write: {} ({e})", destination_path.display()))?;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::config::Config;
+    use crate::services::themers::tests::{
+        assert_snapshot, context, fixture_path, settings, theme,
+    };
+    use crate::utils::file_system::MemoryFs;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn apply_matches_the_snapshot() {
+        let btop_conf_path = Paths::user_home().unwrap().join(".config/btop/btop.conf");
+        let file_system = MemoryFs::default().with_file(
+            btop_conf_path.clone(),
+            &fs::read_to_string(fixture_path("btop.conf")).unwrap(),
+        );
+        let mut theme = theme();
+        theme.btop_theme_path = Some(PathBuf::from("/themes/kanagawa/btop.theme"));
+        let settings = settings(Config::default());
+
+        BtopThemer
+            .apply(&context(&theme, &settings, &[], &file_system))
+            .unwrap();
+
+        assert_snapshot("btop.conf", &file_system.contents(&btop_conf_path).unwrap());
+    }
+
+    #[test]
+    fn apply_replaces_the_color_theme_and_keeps_the_other_settings() {
+        let btop_conf_path = Paths::user_home().unwrap().join(".config/btop/btop.conf");
+        let file_system = MemoryFs::default().with_file(
+            btop_conf_path.clone(),
+            "color_theme = \"Default\"\ntheme_background = False\n",
+        );
+        let mut theme = theme();
+        theme.btop_theme_path = Some(PathBuf::from("/themes/kanagawa/btop.theme"));
+        let settings = settings(Config::default());
+
+        BtopThemer
+            .apply(&context(&theme, &settings, &[], &file_system))
+            .unwrap();
+
+        assert_eq!(
+            file_system.contents(&btop_conf_path).unwrap(),
+            "color_theme = \"/themes/kanagawa/btop.theme\"\ntheme_background = False\n"
+        );
+    }
+}
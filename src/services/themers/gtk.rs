@@ -1,31 +1,49 @@
+use crate::models::component::Component;
 use crate::models::theme::ColorScheme;
 use crate::services::themers::{ThemeContext, Themer};
+use crate::utils::command_runner::CommandRunner;
 use std::process::Command;
 
 pub struct GtkThemer;
 
 impl Themer for GtkThemer {
+    fn component(&self) -> Component {
+        Component::Gtk
+    }
+
     fn apply(&self, context: &ThemeContext<'_>) -> Result<(), String> {
+        // The GTK settings only exist in the running session, there is no file to write instead
+        if !context.reloads_applications() {
+            return Ok(());
+        }
+
         let color_scheme: &str = match context.theme.color_scheme {
             ColorScheme::Light => "prefer-light",
             ColorScheme::Dark => "prefer-dark",
         };
 
-        Command::new("gsettings")
-            .arg("set")
-            .arg("org.gnome.desktop.interface")
-            .arg("color-scheme")
-            .arg(color_scheme)
-            .output()
-            .map_err(|e| format!("Failed to set GTK color scheme: {e}"))?;
-
-        Command::new("gsettings")
-            .arg("set")
-            .arg("org.gnome.desktop.interface")
-            .arg("gtk-theme")
-            .arg(&context.theme.gtk_theme)
-            .output()
-            .map_err(|e| format!("Failed to set GTK theme: {e}"))?;
+        CommandRunner::run(
+            Command::new("gsettings")
+                .arg("set")
+                .arg("org.gnome.desktop.interface")
+                .arg("color-scheme")
+                .arg(color_scheme),
+        )
+        .map_err(|e| format!("Failed to set GTK color scheme: {e}"))?;
+
+        // Without a GTK theme in the metadata, the current one is kept
+        if context.theme.gtk_theme.is_empty() {
+            return Ok(());
+        }
+
+        CommandRunner::run(
+            Command::new("gsettings")
+                .arg("set")
+                .arg("org.gnome.desktop.interface")
+                .arg("gtk-theme")
+                .arg(&context.theme.gtk_theme),
+        )
+        .map_err(|e| format!("Failed to set GTK theme: {e}"))?;
 
         Ok(())
     }
@@ -1,17 +1,33 @@
+use crate::models::component::Component;
+use crate::models::config::Config;
+use crate::models::hex_color::HexColor;
 use crate::services::themers::{ThemeContext, Themer};
+use crate::utils::command_runner::CommandRunner;
 use crate::utils::paths::Paths;
+use std::collections::BTreeMap;
 use std::fmt::Write;
-use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
 
 pub struct HyprThemer;
 
 impl Themer for HyprThemer {
+    fn component(&self) -> Component {
+        Component::Hyprland
+    }
+
     fn apply(&self, context: &ThemeContext<'_>) -> Result<(), String> {
         let style_variables_file_path =
-            Paths::user_home()?.join(".config/hypr/hyprland/style_variables.lua");
+            context.destination(&Self::style_variables_file_path(&context.settings.config)?)?;
 
         let theme_variables_css_file_path = context.theme.get_theme_variables_css_file_path();
 
+        // Read before the file is replaced, so that only the changed variables are set live
+        let previous_output = context
+            .file_system
+            .read_to_string(&style_variables_file_path)
+            .unwrap_or_default();
+
         let mut output = String::new();
 
         writeln!(
@@ -21,35 +37,335 @@ impl Themer for HyprThemer {
         )
         .map_err(|e| format!("Could not write line. {e}"))?;
 
+        let mut written_variables = Vec::new();
+
         for (name, value) in &context.variables {
+            // Hex colors are normalized, so that `#111` is written as `#111111`
+            let value = &if value.starts_with('#') {
+                HexColor::try_from(value).map_or_else(|_| value.clone(), String::from)
+            } else {
+                value.clone()
+            };
+
+            // Rust also parses `inf` and `NaN` as numbers, which Lua would read as variable names.
+            // Quoted SCSS strings, such as a font name, already are Lua strings
             let formatted = if value.parse::<i64>().is_ok()
-                || value.parse::<f64>().is_ok()
+                || value.parse::<f64>().is_ok_and(f64::is_finite)
                 || value == "true"
                 || value == "false"
+                || Self::is_quoted(value)
             {
                 value.clone()
             } else {
-                format!(r#""{value}""#)
+                format!(r#""{}""#, value.replace('\\', r"\\").replace('"', r#"\""#))
             };
 
-            writeln!(output, "_G.{name} = {formatted}")
-                .map_err(|e| format!("Could not write line. {e}"))?;
+            let line = format!("_G.{name} = {formatted}");
+
+            writeln!(output, "{line}").map_err(|e| format!("Could not write line. {e}"))?;
+
+            written_variables.push((name.as_str(), value.clone(), line));
+        }
+
+        context
+            .file_system
+            .create_dir_all(style_variables_file_path.parent().unwrap())
+            .map_err(|e| {
+                format!(
+                    "Could not create parent directories for {}. {e}",
+                    style_variables_file_path.display()
+                )
+            })?;
+
+        context
+            .file_system
+            .write(&style_variables_file_path, &output)
+            .map_err(|e| {
+                format!(
+                    "Could not write to {}. {e}",
+                    style_variables_file_path.display()
+                )
+            })?;
+
+        if context.reloads_applications() && context.settings.config.hypr.live {
+            Self::set_options(&Self::changed_options(
+                &context.settings.config.hypr.options,
+                &previous_output,
+                &written_variables,
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl HyprThemer {
+    /// Sets the options in the running Hyprland with a single `hyprctl --batch` of keywords. If
+    /// Hyprland rejects any of them, the whole configuration is reloaded with `hyprctl reload`
+    /// instead, so that no color is left stale.
+    fn set_options(options: &[(&str, String)]) -> Result<(), String> {
+        if options.is_empty() {
+            return Ok(());
         }
 
-        fs::create_dir_all(style_variables_file_path.parent().unwrap()).map_err(|e| {
+        let keywords = options
+            .iter()
+            .map(|(option, value)| format!("keyword {option} {value}"))
+            .collect::<Vec<_>>()
+            .join(" ; ");
+
+        let Err(e) = Self::run_hyprctl(Command::new("hyprctl").arg("--batch").arg(keywords)) else {
+            return Ok(());
+        };
+
+        Self::run_hyprctl(Command::new("hyprctl").arg("reload")).map_err(|reload_error| {
             format!(
-                "Could not create parent directories for {}. {e}",
-                style_variables_file_path.display()
+                "Could not set options in Hyprland: {e}. Could not reload Hyprland either. {reload_error}"
             )
-        })?;
+        })
+    }
+
+    /// Runs `hyprctl`, which exits successfully even when Hyprland rejects the request. Any
+    /// output other than `ok` is therefore treated as an error.
+    fn run_hyprctl(command: &mut Command) -> Result<(), String> {
+        let output = CommandRunner::run(command)?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        match stdout
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && *line != "ok")
+        {
+            Some(error) => Err(format!("Hyprland answered \"{error}\"")),
+            None => Ok(()),
+        }
+    }
+
+    /// Gets the configured options whose variables have lines that are new in the style
+    /// variables file, compared to the previously written one, with the values Hyprland reads.
+    fn changed_options<'a>(
+        options: &'a BTreeMap<String, String>,
+        previous_output: &str,
+        written_variables: &[(&str, String, String)],
+    ) -> Vec<(&'a str, String)> {
+        let previous_lines: Vec<&str> = previous_output.lines().collect();
+
+        options
+            .iter()
+            .filter_map(|(option, variable)| {
+                let (_, value, _) = written_variables.iter().find(|(name, _, line)| {
+                    *name == variable.as_str() && !previous_lines.contains(&line.as_str())
+                })?;
+
+                Some((option.as_str(), Self::option_value(value)))
+            })
+            .collect()
+    }
+
+    /// Converts a variable's value to an option value. Quoted strings are unquoted, and hex
+    /// colors are written as `rgb(...)` or `rgba(...)`, since Hyprland reads `#` as a comment.
+    fn option_value(value: &str) -> String {
+        let value = if Self::is_quoted(value) {
+            &value[1..value.len() - 1]
+        } else {
+            value
+        };
 
-        fs::write(&style_variables_file_path, output).map_err(|e| {
+        match value.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 => format!("rgb({hex})"),
+            Some(hex) if hex.len() == 8 => format!("rgba({hex})"),
+            _ => value.to_string(),
+        }
+    }
+
+    /// Whether the value is a string in single or double quotes.
+    fn is_quoted(value: &str) -> bool {
+        value.len() >= 2
+            && ((value.starts_with('"') && value.ends_with('"'))
+                || (value.starts_with('\'') && value.ends_with('\'')))
+    }
+
+    /// Gets the path to the generated Hyprland style variables file, located at
+    /// `~/.config/hypr/hyprland/style_variables.lua` unless configured otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable `HOME` is not set, or if the configured path
+    /// is invalid.
+    ///
+    pub fn style_variables_file_path(config: &Config) -> Result<PathBuf, String> {
+        if let Some(path) = &config.paths.hypr {
+            return Paths::expand(path);
+        }
+
+        Ok(Paths::user_home()?.join(".config/hypr/hyprland/style_variables.lua"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::themers::tests::{
+        assert_snapshot, context, fixture_variables, hypr_context,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn apply_writes_the_variables_as_lua_globals() {
+        let (file_system, settings, theme) = hypr_context();
+
+        HyprThemer
+            .apply(&context(
+                &theme,
+                &settings,
+                &[("backgroundColor", "#111")],
+                &file_system,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            file_system
+                .contents(Path::new("/hypr/style_variables.lua"))
+                .unwrap(),
             format!(
-                "Could not write to {}. {e}",
-                style_variables_file_path.display()
+                "-- Autogenerated from {}\n_G.backgroundColor = \"#111111\"\n",
+                theme.get_theme_variables_css_file_path().display()
             )
-        })?;
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn apply_matches_the_snapshot() {
+        let (file_system, settings, theme) = hypr_context();
+        let mut context = context(&theme, &settings, &[], &file_system);
+        context.variables = fixture_variables();
+
+        HyprThemer.apply(&context).unwrap();
+
+        assert_snapshot(
+            "hypr-style_variables.lua",
+            &file_system
+                .contents(Path::new("/hypr/style_variables.lua"))
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn apply_writes_values_that_are_not_colors_as_authored() {
+        let (file_system, settings, theme) = hypr_context();
+
+        HyprThemer
+            .apply(&context(
+                &theme,
+                &settings,
+                &[
+                    ("activeBorderColor", "#7e9cd8"),
+                    ("blur_size", "8"),
+                    ("opacity", "0.9"),
+                    ("gaps", "5px"),
+                    ("font", "JetBrains Mono"),
+                    ("shadowColor", "rgba(0, 0, 0, 0.5)"),
+                ],
+                &file_system,
+            ))
+            .unwrap();
+
+        let style_variables = file_system
+            .contents(Path::new("/hypr/style_variables.lua"))
+            .unwrap();
+
+        assert!(style_variables.ends_with(
+            "_G.activeBorderColor = \"#7E9CD8\"\n\
+             _G.blur_size = 8\n\
+             _G.opacity = 0.9\n\
+             _G.gaps = \"5px\"\n\
+             _G.font = \"JetBrains Mono\"\n\
+             _G.shadowColor = \"rgba(0, 0, 0, 0.5)\"\n"
+        ));
+    }
+
+    #[test]
+    fn apply_writes_valid_lua_strings() {
+        let (file_system, settings, theme) = hypr_context();
+
+        HyprThemer
+            .apply(&context(
+                &theme,
+                &settings,
+                &[
+                    ("font", "\"JetBrains Mono\""),
+                    ("icons", "'Papirus'"),
+                    ("background", "url(\"bg.png\")"),
+                ],
+                &file_system,
+            ))
+            .unwrap();
+
+        let style_variables = file_system
+            .contents(Path::new("/hypr/style_variables.lua"))
+            .unwrap();
+
+        assert!(style_variables.ends_with(
+            "_G.font = \"JetBrains Mono\"\n\
+             _G.icons = 'Papirus'\n\
+             _G.background = \"url(\\\"bg.png\\\")\"\n"
+        ));
+    }
+
+    #[test]
+    fn changed_options_only_include_the_variables_that_changed() {
+        let previous_output = "-- Autogenerated from /themes/kanagawa/theme-variables.css\n\
+                               _G.activeBorderColor = \"#111111\"\n\
+                               _G.borderSize = 2\n";
+        let written_variables = [
+            (
+                "activeBorderColor",
+                "#1A1B26".to_string(),
+                "_G.activeBorderColor = \"#1A1B26\"".to_string(),
+            ),
+            (
+                "borderSize",
+                "2".to_string(),
+                "_G.borderSize = 2".to_string(),
+            ),
+            (
+                "shadowColor",
+                "#00000080".to_string(),
+                "_G.shadowColor = \"#00000080\"".to_string(),
+            ),
+            ("gaps", "5px".to_string(), "_G.gaps = \"5px\"".to_string()),
+        ];
+        let options = BTreeMap::from([
+            (
+                "decoration:shadow:color".to_string(),
+                "shadowColor".to_string(),
+            ),
+            ("general:border_size".to_string(), "borderSize".to_string()),
+            (
+                "general:col.active_border".to_string(),
+                "activeBorderColor".to_string(),
+            ),
+        ]);
+
+        assert_eq!(
+            HyprThemer::changed_options(&options, previous_output, &written_variables),
+            vec![
+                ("decoration:shadow:color", "rgba(00000080)".to_string()),
+                ("general:col.active_border", "rgb(1A1B26)".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn option_value_unquotes_strings_like_is_quoted() {
+        assert_eq!(
+            HyprThemer::option_value("\"JetBrains Mono\""),
+            "JetBrains Mono"
+        );
+        assert_eq!(HyprThemer::option_value("'Papirus'"), "Papirus");
+        assert_eq!(
+            HyprThemer::option_value("url(\"bg.png\")"),
+            "url(\"bg.png\")"
+        );
     }
 }
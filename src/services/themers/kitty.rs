@@ -1,26 +1,38 @@
-use crate::models::hex_color::HexColor;
+use crate::models::component::Component;
+use crate::models::config::Config;
 use crate::services::themers::{ThemeContext, Themer};
+use crate::utils::command_runner::CommandRunner;
+use crate::utils::file_system::FileSystem;
 use crate::utils::paths::Paths;
-use regex::Regex;
+use crate::utils::template::Template;
+use std::env;
 use std::fmt::Write;
-use std::fs;
-use std::process::{Command, Stdio};
+use std::path::PathBuf;
+use std::time::Duration;
 
 pub struct KittyThemer;
 
 impl Themer for KittyThemer {
+    fn component(&self) -> Component {
+        Component::Kitty
+    }
+
     fn apply(&self, context: &ThemeContext<'_>) -> Result<(), String> {
-        let home_path = Paths::user_home()?;
-        let kitty_theme_conf_file_path = &home_path.join(".config/kitty/theme.conf");
-        let theme_template_file_path = &home_path.join(".config/kitty/theme-template.conf");
+        let config_dir_path = Self::config_dir_path(&context.settings.config)?;
+        let kitty_theme_conf_file_path =
+            &context.destination(&config_dir_path.join("theme.conf"))?;
+        let theme_template_file_path = &config_dir_path.join("theme-template.conf");
 
-        let kitty_theme_template = fs::read_to_string(theme_template_file_path).map_err(|e| {
-            format!(
-                "Could not read contents of {}: {}",
-                theme_template_file_path.display(),
-                e
-            )
-        })?;
+        let kitty_theme_template = context
+            .file_system
+            .read_to_string(theme_template_file_path)
+            .map_err(|e| {
+                format!(
+                    "Could not read contents of {}: {}",
+                    theme_template_file_path.display(),
+                    e
+                )
+            })?;
 
         let mut output = String::new();
 
@@ -31,52 +43,252 @@ impl Themer for KittyThemer {
         )
         .map_err(|e| format!("Could not write line. {e}"))?;
 
-        let replacement_variable_regex = Regex::new(r"__(:?.*)__").unwrap();
+        output.push_str(&Template::render(
+            &kitty_theme_template,
+            &context.variables,
+            kitty_theme_conf_file_path,
+        )?);
 
-        for line in kitty_theme_template.lines() {
-            let Some(captures) = replacement_variable_regex.captures(line) else {
-                writeln!(output, "{line}").map_err(|e| format!("Could not write line. {e}"))?;
-                continue;
-            };
+        if let Some(parent) = kitty_theme_conf_file_path.parent() {
+            context
+                .file_system
+                .create_dir_all(parent)
+                .map_err(|e| format!("Could not create {}. {e}", parent.display()))?;
+        }
 
-            if captures.len() != 2 {
-                writeln!(output, "{line}").map_err(|e| format!("Could not write line. {e}"))?;
-                continue;
-            }
+        context
+            .file_system
+            .write(kitty_theme_conf_file_path, &output)
+            .map_err(|e| {
+                format!(
+                    "Could not write {}. {e}",
+                    kitty_theme_conf_file_path.display()
+                )
+            })?;
 
-            let replacement_variable = captures[0].to_string();
-            let variable_name = captures[1].to_string();
+        if !context.reloads_applications() {
+            return Ok(());
+        }
 
-            let Some(variable) = context.variables.iter().find(|v| v.0 == variable_name) else {
-                writeln!(output, "{line}").map_err(|e| format!("Could not write line. {e}"))?;
-                continue;
-            };
+        Self::reload()
+    }
+}
+
+impl KittyThemer {
+    /// Environment variable that overrides the command used to make Kitty reload its config.
+    const RELOAD_COMMAND_ENV: &'static str = "NORLYK_KITTY_RELOAD_COMMAND";
+
+    const DEFAULT_RELOAD_COMMAND: &'static str = "kitty @ load-config";
 
-            let variable_value = &variable.1;
-            let hex_color: HexColor = variable_value.try_into()?;
-            let hex_string: String = hex_color.into();
-            let new_line = line.replace(&replacement_variable, &hex_string);
+    /// How long to wait for Kitty to reload its config, so an unresponsive Kitty does not hold up
+    /// applying the theme.
+    const RELOAD_TIMEOUT: Duration = Duration::from_secs(2);
 
-            writeln!(output, "{new_line}").map_err(|e| format!("Could not write line. {e}"))?;
+    /// Gets the path to Kitty's config directory, which is the configured path if set, otherwise
+    /// `$KITTY_CONFIG_DIRECTORY` if set, otherwise `~/.config/kitty`.
+    fn config_dir_path(config: &Config) -> Result<PathBuf, String> {
+        if let Some(path) = &config.paths.kitty {
+            return Paths::expand(path);
         }
 
-        fs::write(kitty_theme_conf_file_path, output).map_err(|e| {
+        if let Ok(path) = env::var("KITTY_CONFIG_DIRECTORY")
+            && !path.is_empty()
+        {
+            return Ok(PathBuf::from(path));
+        }
+
+        Ok(Paths::user_home()?.join(".config/kitty"))
+    }
+
+    /// Gets the path to the template that Kitty's `theme.conf` is generated from.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Kitty's config directory cannot be resolved.
+    pub fn template_file_path(config: &Config) -> Result<PathBuf, String> {
+        Ok(Self::config_dir_path(config)?.join("theme-template.conf"))
+    }
+
+    /// Finds the placeholders in Kitty's `theme-template.conf` that none of the variables resolve,
+    /// reading the template from the file system.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if Kitty's config directory cannot be resolved, or the template cannot be
+    /// read.
+    pub fn unresolved_placeholders(
+        config: &Config,
+        variables: &[(String, String)],
+        file_system: &dyn FileSystem,
+    ) -> Result<Vec<String>, String> {
+        let theme_template_file_path = Self::template_file_path(config)?;
+        let kitty_theme_template = file_system
+            .read_to_string(&theme_template_file_path)
+            .map_err(|e| {
+                format!(
+                    "Could not read contents of {}: {e}",
+                    theme_template_file_path.display()
+                )
+            })?;
+
+        Ok(Template::unresolved_placeholders(
+            &kitty_theme_template,
+            variables,
+        ))
+    }
+
+    /// Asks Kitty to reload its configuration, using the command in
+    /// [`Self::RELOAD_COMMAND_ENV`] if set, e.g. `kitty @ --to unix:/tmp/kitty load-config`.
+    ///
+    /// The command is given [`Self::RELOAD_TIMEOUT`] to finish.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error with what the command wrote to stderr, and a hint on enabling remote
+    /// control, if the command cannot be executed, is rejected by Kitty, or does not finish in
+    /// time. The theme file has already been written then, and Kitty picks it up on the next start.
+    fn reload() -> Result<(), String> {
+        let reload_command = env::var(Self::RELOAD_COMMAND_ENV)
+            .ok()
+            .filter(|command| !command.trim().is_empty())
+            .unwrap_or_else(|| Self::DEFAULT_RELOAD_COMMAND.to_string());
+
+        Self::run_reload_command(&reload_command)
+    }
+
+    fn run_reload_command(reload_command: &str) -> Result<(), String> {
+        let mut command = CommandRunner::parse(reload_command)
+            .map_err(|e| format!("Kitty did not reload its config. {e}"))?;
+
+        let failure = match CommandRunner::output_with_timeout(&mut command, Self::RELOAD_TIMEOUT) {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => Some(CommandRunner::describe_failure(&command, &output)),
+            Err(e) => Some(format!("Could not execute '{reload_command}'. {e}")),
+        };
+
+        match failure {
+            Some(failure) => Err(format!(
+                "Kitty did not reload its config. {failure}\nHint: set 'allow_remote_control yes' in \
+                 kitty.conf, or set {} to a working command",
+                Self::RELOAD_COMMAND_ENV
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::themers::tests::{
+        assert_snapshot, context, fixture_path, fixture_variables, kitty_context,
+    };
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn apply_writes_the_theme_from_the_template() {
+        let (file_system, settings, theme) = kitty_context("background __backgroundColor__\n");
+
+        KittyThemer
+            .apply(&context(
+                &theme,
+                &settings,
+                &[("backgroundColor", "#111")],
+                &file_system,
+            ))
+            .unwrap();
+
+        assert_eq!(
+            file_system
+                .contents(Path::new("/kitty/theme.conf"))
+                .unwrap(),
             format!(
-                "Could not write {}. {e}",
-                kitty_theme_conf_file_path.display()
+                "# Autogenerated from {}\nbackground #111111\n",
+                theme.get_theme_variables_css_file_path().display()
             )
-        })?;
-
-        Command::new("kitty")
-            .arg("@")
-            .arg("--no-response")
-            .arg("load-config")
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .map_err(|e| format!("Could not execute 'load-config' command for Kitty. {e}"))?;
-
-        Ok(())
+        );
+    }
+
+    #[test]
+    fn apply_fills_in_values_that_are_not_colors_as_they_are() {
+        let (file_system, settings, theme) = kitty_context(
+            "background __backgroundColor__\nfont_size __fontSize__\nfont_family __font__\n\
+             cursor __cursorColor__\n",
+        );
+
+        KittyThemer
+            .apply(&context(
+                &theme,
+                &settings,
+                &[
+                    ("backgroundColor", "#1f1f28"),
+                    ("fontSize", "11.5"),
+                    ("font", "JetBrains Mono"),
+                    ("cursorColor", "rgba(220, 215, 186, 1)"),
+                ],
+                &file_system,
+            ))
+            .unwrap();
+
+        let theme_conf = file_system
+            .contents(Path::new("/kitty/theme.conf"))
+            .unwrap();
+
+        assert!(theme_conf.ends_with(
+            "background #1F1F28\nfont_size 11.5\nfont_family JetBrains Mono\ncursor #DCD7BA\n"
+        ));
+    }
+
+    #[test]
+    fn apply_matches_the_snapshot() {
+        let (file_system, settings, theme) =
+            kitty_context(&fs::read_to_string(fixture_path("kitty-theme-template.conf")).unwrap());
+        let mut context = context(&theme, &settings, &[], &file_system);
+        context.variables = fixture_variables();
+
+        KittyThemer.apply(&context).unwrap();
+
+        assert_snapshot(
+            "kitty-theme.conf",
+            &file_system
+                .contents(Path::new("/kitty/theme.conf"))
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn unresolved_placeholders_reads_the_template_through_the_file_system() {
+        let (file_system, settings, _) =
+            kitty_context("background __backgroundColor__\ncursor __cursorColor__\n");
+
+        assert_eq!(
+            KittyThemer::unresolved_placeholders(
+                &settings.config,
+                &[("backgroundColor".to_string(), "#111".to_string())],
+                &file_system,
+            ),
+            Ok(vec!["cursorColor".to_string()])
+        );
+    }
+
+    #[test]
+    fn reload_succeeds_when_the_command_does() {
+        assert_eq!(KittyThemer::run_reload_command("true"), Ok(()));
+    }
+
+    #[test]
+    fn reload_hints_at_remote_control_when_kitty_rejects_the_command() {
+        let error = KittyThemer::run_reload_command("false").unwrap_err();
+
+        assert!(error.contains("allow_remote_control"), "{error}");
+    }
+
+    #[test]
+    fn reload_hints_at_remote_control_when_the_command_cannot_be_executed() {
+        let error =
+            KittyThemer::run_reload_command("norlyk-missing-kitty @ load-config").unwrap_err();
+
+        assert!(error.contains("allow_remote_control"), "{error}");
     }
 }
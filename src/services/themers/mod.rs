@@ -4,10 +4,20 @@ pub mod hypr;
 pub mod kitty;
 pub mod nvim;
 pub mod waybar;
+pub mod wezterm;
 
+use crate::models::component::Component;
+use crate::models::config::WaybarReload;
+use crate::models::settings::Settings;
 use crate::models::theme::Theme;
+use crate::utils::file_system::FileSystem;
+use crate::utils::paths::Paths;
+use std::path::{Path, PathBuf};
 
 pub trait Themer {
+    /// The application or service the theme is applied to.
+    fn component(&self) -> Component;
+
     /// Applies a theme to a specific target application or service.
     ///
     /// Implementations may update configuration files, create directories,
@@ -26,5 +36,166 @@ pub trait Themer {
 
 pub struct ThemeContext<'a> {
     pub theme: &'a Theme,
+    /// The directories of the theme picker and the global configuration.
+    pub settings: &'a Settings,
     pub variables: Vec<(String, String)>,
+    /// Used to read templates and write the generated configuration files.
+    pub file_system: &'a dyn FileSystem,
+    /// The directory the generated configuration files are written under, instead of the home
+    /// directory. See [`ThemeContext::destination()`].
+    pub output_dir: Option<PathBuf>,
+    /// Whether running applications are told to pick up the theme. See
+    /// [`ThemeContext::reloads_applications()`].
+    pub reload_applications: bool,
+    /// Overrides how Waybar is reloaded, instead of the configured [`WaybarReload`].
+    pub waybar_reload: Option<WaybarReload>,
+}
+
+impl ThemeContext<'_> {
+    /// Gets the path a generated configuration file is written to. Without an output directory,
+    /// this is the path itself. Otherwise, the path relative to the home directory is placed
+    /// under the output directory, e.g. `~/.config/kitty/theme.conf` is written to
+    /// `<output-dir>/.config/kitty/theme.conf`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable `HOME` is not set.
+    pub fn destination(&self, path: &Path) -> Result<PathBuf, String> {
+        let Some(output_dir) = &self.output_dir else {
+            return Ok(path.to_path_buf());
+        };
+
+        let home_path = Paths::user_home()?;
+        let relative_path = path
+            .strip_prefix(&home_path)
+            .or_else(|_| path.strip_prefix("/"))
+            .unwrap_or(path);
+
+        Ok(output_dir.join(relative_path))
+    }
+
+    /// Whether running applications are told to pick up the theme, which they are not when
+    /// reloading is turned off or the files are written to an output directory.
+    #[must_use]
+    pub fn reloads_applications(&self) -> bool {
+        self.reload_applications && self.output_dir.is_none()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::models::config::Config;
+    use crate::models::theme::ColorScheme;
+    use crate::services::theme_service::ThemeService;
+    use crate::utils::file_system::MemoryFs;
+    use std::{env, fs};
+
+    /// The directory with the generated configuration files that the themers' output is compared
+    /// against, and the `fixture/` theme they are generated from. Setting `UPDATE_SNAPSHOTS=1`
+    /// writes the output to the snapshots instead, for when the format changes on purpose.
+    pub(crate) const SNAPSHOTS_DIR: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/services/themers/snapshots"
+    );
+
+    /// Builds a theme in `/themes/kanagawa`, which is never read by the themers.
+    pub(crate) fn theme() -> Theme {
+        Theme::new(
+            "Kanagawa",
+            "",
+            PathBuf::from("/themes/kanagawa"),
+            None,
+            ColorScheme::Dark,
+            "",
+            Theme::DEFAULT_VARIABLES_FILE_NAME,
+        )
+    }
+
+    /// Builds the settings with the given configuration and the themes in `/themes`.
+    pub(crate) fn settings(config: Config) -> Settings {
+        Settings {
+            paths: Paths::new(PathBuf::from("/themes"), PathBuf::from("/themes")),
+            config,
+        }
+    }
+
+    /// Builds a context applying the theme with the variables to the file system, without
+    /// reloading any applications.
+    pub(crate) fn context<'a>(
+        theme: &'a Theme,
+        settings: &'a Settings,
+        variables: &[(&str, &str)],
+        file_system: &'a dyn FileSystem,
+    ) -> ThemeContext<'a> {
+        ThemeContext {
+            theme,
+            settings,
+            variables: variables
+                .iter()
+                .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+                .collect(),
+            file_system,
+            output_dir: None,
+            reload_applications: false,
+            waybar_reload: None,
+        }
+    }
+
+    /// Builds a file system with Kitty's `theme-template.conf` in `/kitty`, the settings reading
+    /// Kitty's configuration from there, and the theme.
+    pub(crate) fn kitty_context(template: &str) -> (MemoryFs, Settings, Theme) {
+        let mut config = Config::default();
+        config.paths.kitty = Some("/kitty".to_string());
+
+        (
+            MemoryFs::default().with_file("/kitty/theme-template.conf", template),
+            settings(config),
+            theme(),
+        )
+    }
+
+    /// Builds an empty file system, the settings writing Hyprland's style variables to
+    /// `/hypr/style_variables.lua`, and the theme.
+    pub(crate) fn hypr_context() -> (MemoryFs, Settings, Theme) {
+        let mut config = Config::default();
+        config.paths.hypr = Some("/hypr/style_variables.lua".to_string());
+
+        (MemoryFs::default(), settings(config), theme())
+    }
+
+    /// Gets the path to a file of the fixture theme.
+    pub(crate) fn fixture_path(name: &str) -> PathBuf {
+        Path::new(SNAPSHOTS_DIR).join("fixture").join(name)
+    }
+
+    /// Gets the variables of the fixture theme, as collected when applying it.
+    pub(crate) fn fixture_variables() -> Vec<(String, String)> {
+        let mut fixture_theme = theme();
+        fixture_theme.directory_path = fixture_path("");
+
+        ThemeService::get_variables(&fixture_theme).unwrap()
+    }
+
+    /// Compares the generated configuration file with its snapshot.
+    pub(crate) fn assert_snapshot(name: &str, actual: &str) {
+        let snapshot_path = Path::new(SNAPSHOTS_DIR).join(name);
+
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::write(&snapshot_path, actual).unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(&snapshot_path).unwrap_or_else(|e| {
+            panic!(
+                "Could not read {}, run with UPDATE_SNAPSHOTS=1 to create it: {e}",
+                snapshot_path.display()
+            )
+        });
+
+        assert_eq!(
+            actual, expected,
+            "{name} differs from its snapshot, run with UPDATE_SNAPSHOTS=1 if the change is intended"
+        );
+    }
 }
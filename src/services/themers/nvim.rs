@@ -1,3 +1,4 @@
+use crate::models::component::Component;
 use crate::services::themers::{ThemeContext, Themer};
 use crate::utils::paths::Paths;
 use crate::utils::symlink::Symlink;
@@ -6,9 +7,13 @@ use std::fs;
 pub struct NvimThemer;
 
 impl Themer for NvimThemer {
-    fn apply(&self, _context: &ThemeContext<'_>) -> Result<(), String> {
-        let nvim_color_scheme_file_path =
-            Paths::user_home()?.join(".config/nvim/lua/plugins/colorscheme.lua");
+    fn component(&self) -> Component {
+        Component::Neovim
+    }
+
+    fn apply(&self, context: &ThemeContext<'_>) -> Result<(), String> {
+        let nvim_color_scheme_file_path = context
+            .destination(&Paths::user_home()?.join(".config/nvim/lua/plugins/colorscheme.lua"))?;
 
         if Symlink::exists(&nvim_color_scheme_file_path)? {
             fs::remove_file(&nvim_color_scheme_file_path).map_err(|e| {
@@ -19,7 +24,11 @@ impl Themer for NvimThemer {
             })?;
         }
 
-        let theme_color_scheme_file_path = Paths::current_theme()?.join("nvim-colorscheme.lua");
+        let theme_color_scheme_file_path = context
+            .settings
+            .paths
+            .current_theme()
+            .join("nvim-colorscheme.lua");
 
         if !fs::exists(&theme_color_scheme_file_path).map_err(|e| {
             format!(
@@ -30,6 +39,13 @@ impl Themer for NvimThemer {
             return Ok(());
         }
 
+        if let Some(parent) = nvim_color_scheme_file_path.parent() {
+            context
+                .file_system
+                .create_dir_all(parent)
+                .map_err(|e| format!("Could not create {}: {e}", parent.display()))?;
+        }
+
         Symlink::create(&theme_color_scheme_file_path, &nvim_color_scheme_file_path)
     }
 }
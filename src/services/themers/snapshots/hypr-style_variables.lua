@@ -0,0 +1,12 @@
+-- Autogenerated from /themes/kanagawa/theme-variables.scss
+_G.backgroundColor = "rgba(31,31,40,1)"
+_G.foregroundColor = "rgba(220,215,186,1)"
+_G.accentColor = "#7E9CD8"
+_G.borderColor = "rgba(220,215,186,0.65)"
+_G.cursorColor = "#C8C093"
+_G.terminal_opacity = 0.95
+_G.fontSize = 11.5
+_G.font = "JetBrains Mono"
+_G.termFont = "JetBrains Mono Nerd Font"
+_G.gaps = "5px"
+_G.rounding = 8
@@ -1,35 +1,221 @@
+use crate::models::component::Component;
+use crate::models::config::{Config, WaybarReload};
 use crate::services::themers::{ThemeContext, Themer};
+use crate::utils::command_runner::CommandRunner;
 use crate::utils::paths::Paths;
-use std::process::{Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 pub struct WaybarThemer;
 
 impl Themer for WaybarThemer {
-    fn apply(&self, _context: &ThemeContext<'_>) -> Result<(), String> {
-        let config_path = Paths::config_path()?;
-        let theme_waybar_style_path = config_path.join("waybar-style.scss");
-        let actual_waybar_style_path = Paths::user_home()?.join(".config/waybar/style.css");
-
-        Command::new("sass")
-            .arg("--no-source-map")
-            .arg(theme_waybar_style_path)
-            .arg(actual_waybar_style_path)
-            .output()
-            .map_err(|e| format!("Failed to compile .css file: {e}"))?;
-
-        Command::new("pkill")
-            .arg("waybar")
-            .output()
-            .map_err(|e| format!("Failed to stop waybar: {e}"))?;
-
-        Command::new("nohup")
-            .arg("waybar")
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
+    fn component(&self) -> Component {
+        Component::Waybar
+    }
+
+    fn apply(&self, context: &ThemeContext<'_>) -> Result<(), String> {
+        let config = &context.settings.config;
+        let actual_waybar_style_path = context.destination(&Self::style_file_path(config)?)?;
+
+        if let Some(parent) = actual_waybar_style_path.parent() {
+            context
+                .file_system
+                .create_dir_all(parent)
+                .map_err(|e| format!("Could not create {}: {e}", parent.display()))?;
+        }
+
+        CommandRunner::run(&mut Self::compile_command(
+            context,
+            &actual_waybar_style_path,
+        )?)
+        .map_err(|e| format!("Failed to compile .css file: {e}"))?;
+
+        context
+            .file_system
+            .written_externally(&actual_waybar_style_path);
+
+        if !context.reloads_applications() {
+            return Ok(());
+        }
+
+        let reload = match context.waybar_reload {
+            Some(reload) => reload,
+            None => config.waybar.reload,
+        };
+
+        match reload {
+            WaybarReload::Restart => Self::restart(),
+            WaybarReload::Signal => Self::signal_reload(),
+            WaybarReload::None => Ok(()),
+        }
+    }
+}
+
+impl WaybarThemer {
+    const DEFAULT_SASS_COMMAND: &'static str = "sass --no-source-map";
+
+    /// Gets the command compiling the stylesheet, which is `sass --no-source-map` unless
+    /// configured otherwise. The configured command is split the way a shell would.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configured command has unclosed quotes.
+    pub fn sass_command(config: &Config) -> Result<Command, String> {
+        let command_line = config
+            .waybar
+            .sass
+            .as_deref()
+            .filter(|command| !command.trim().is_empty())
+            .unwrap_or(Self::DEFAULT_SASS_COMMAND);
+
+        CommandRunner::parse(command_line)
+    }
+
+    /// Builds the command compiling the theme's `waybar-style.scss` to the stylesheet.
+    fn compile_command(
+        context: &ThemeContext<'_>,
+        actual_waybar_style_path: &Path,
+    ) -> Result<Command, String> {
+        let config_path = context.settings.paths.config_path();
+        let mut command = Self::sass_command(&context.settings.config)?;
+
+        // When the state is kept elsewhere, `current/` is looked up in the state directory, and the
+        // theme's `@use "system-variables"` in the theme directory
+        let state_path = context.settings.paths.state_path();
+        if state_path != config_path {
+            command
+                .arg(format!("--load-path={}", state_path.display()))
+                .arg(format!("--load-path={}", config_path.display()));
+        }
+
+        command
+            .arg(config_path.join("waybar-style.scss"))
+            .arg(actual_waybar_style_path);
+
+        Ok(command)
+    }
+
+    /// Stops Waybar, if it is running, and starts it again.
+    fn restart() -> Result<(), String> {
+        let mut pkill = Command::new("pkill");
+        pkill.arg("waybar");
+
+        let output =
+            CommandRunner::output(&mut pkill).map_err(|e| format!("Failed to stop waybar: {e}"))?;
+
+        // pkill exits with 1 when no process matched, i.e. waybar wasn't running
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            return Err(format!(
+                "Failed to stop waybar: {}",
+                CommandRunner::describe_failure(&pkill, &output)
+            ));
+        }
+
+        CommandRunner::spawn_detached("waybar")
             .map_err(|e| format!("Failed to start waybar: {e}"))?;
 
         Ok(())
     }
+
+    /// Asks a running Waybar to reload its configuration and stylesheet with `SIGUSR2`.
+    fn signal_reload() -> Result<(), String> {
+        let mut pkill = Command::new("pkill");
+        pkill.arg("-SIGUSR2").arg("-x").arg("waybar");
+
+        let output = CommandRunner::output(&mut pkill)
+            .map_err(|e| format!("Failed to signal waybar: {e}"))?;
+
+        // pkill exits with 1 when no process matched, i.e. there is no waybar to reload
+        if !matches!(output.status.code(), Some(0 | 1)) {
+            return Err(format!(
+                "Failed to signal waybar: {}",
+                CommandRunner::describe_failure(&pkill, &output)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the path to the compiled Waybar stylesheet, located at `~/.config/waybar/style.css`
+    /// unless configured otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable `HOME` is not set, or if the configured path
+    /// is invalid.
+    ///
+    pub fn style_file_path(config: &Config) -> Result<PathBuf, String> {
+        if let Some(path) = &config.paths.waybar {
+            return Paths::expand(path);
+        }
+
+        Ok(Paths::user_home()?.join(".config/waybar/style.css"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::settings::Settings;
+    use crate::services::themers::tests::{
+        assert_snapshot, context, fixture_path, fixture_variables, settings, theme,
+    };
+    use crate::utils::file_system::{MemoryFs, SystemFs};
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn compile_command_matches_the_snapshot() {
+        let settings = Settings {
+            paths: Paths::new(PathBuf::from("/themes"), PathBuf::from("/state")),
+            ..settings(Config::default())
+        };
+        let theme = theme();
+        let file_system = MemoryFs::default();
+
+        let command = WaybarThemer::compile_command(
+            &context(&theme, &settings, &[], &file_system),
+            Path::new("/waybar/style.css"),
+        )
+        .unwrap();
+
+        assert_snapshot(
+            "waybar-sass-command.txt",
+            &format!("{}\n", CommandRunner::describe(&command)),
+        );
+    }
+
+    #[test]
+    fn sass_command_is_split_like_a_shell_would() {
+        let mut config = Config::default();
+        config.waybar.sass = Some("'/opt/dart sass/sass' --no-source-map".to_string());
+
+        assert_eq!(
+            CommandRunner::describe(&WaybarThemer::sass_command(&config).unwrap()),
+            "/opt/dart sass/sass --no-source-map"
+        );
+
+        config.waybar.sass = Some("'/opt/dart sass/sass".to_string());
+        assert!(WaybarThemer::sass_command(&config).is_err());
+    }
+
+    #[test]
+    #[ignore = "compiles the stylesheet with sass, which must be installed"]
+    fn apply_matches_the_snapshot() {
+        let output_dir = TempDir::new().unwrap();
+        let style_path = output_dir.path().join("style.css");
+        let mut config = Config::default();
+        config.paths.waybar = Some(style_path.display().to_string());
+        let settings = Settings {
+            paths: Paths::new(fixture_path(""), fixture_path("")),
+            ..settings(config)
+        };
+        let theme = theme();
+        let mut context = context(&theme, &settings, &[], &SystemFs);
+        context.variables = fixture_variables();
+
+        WaybarThemer.apply(&context).unwrap();
+
+        assert_snapshot("waybar-style.css", &fs::read_to_string(style_path).unwrap());
+    }
 }
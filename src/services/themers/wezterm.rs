@@ -0,0 +1,194 @@
+use crate::models::component::Component;
+use crate::models::hex_color::HexColor;
+use crate::services::themers::{ThemeContext, Themer};
+use crate::utils::paths::Paths;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub struct WeztermThemer;
+
+impl Themer for WeztermThemer {
+    fn component(&self) -> Component {
+        Component::Wezterm
+    }
+
+    /// Writes the theme as the `norlyk` color scheme. `WezTerm` only watches its configuration
+    /// file and the Lua files it requires, not the color schemes, so it is made to reload by
+    /// touching `wezterm.lua`.
+    fn apply(&self, context: &ThemeContext<'_>) -> Result<(), String> {
+        let config_dir_path = Self::config_dir_path()?;
+
+        // WezTerm is not installed, or not configured, so there is nothing to theme
+        if !config_dir_path.is_dir() {
+            return Ok(());
+        }
+
+        let color_scheme_file_path =
+            context.destination(&config_dir_path.join("colors/norlyk.toml"))?;
+        let output = Self::color_scheme(context)?;
+
+        if let Some(parent) = color_scheme_file_path.parent() {
+            context
+                .file_system
+                .create_dir_all(parent)
+                .map_err(|e| format!("Could not create {}. {e}", parent.display()))?;
+        }
+
+        context
+            .file_system
+            .write(&color_scheme_file_path, &output)
+            .map_err(|e| format!("Could not write {}. {e}", color_scheme_file_path.display()))?;
+
+        if context.reloads_applications() {
+            Self::reload(&config_dir_path.join("wezterm.lua"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl WeztermThemer {
+    /// The theme variables the 8 ANSI colors fall back to, in `WezTerm`'s order: black, red, green,
+    /// yellow, blue, magenta, cyan and white.
+    const ANSI_VARIABLES: [&'static str; 8] = [
+        "backgroundColor",
+        "errorColor",
+        "secondaryColor",
+        "warningColor",
+        "ternaryColor",
+        "primaryColor",
+        "borderColor",
+        "foregroundColor",
+    ];
+
+    /// Gets the path to `WezTerm`'s config directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable `HOME` is not set.
+    pub fn config_dir_path() -> Result<PathBuf, String> {
+        Ok(Paths::user_home()?.join(".config/wezterm"))
+    }
+
+    /// Generates a `WezTerm` color scheme from the theme's variables. The 16 terminal colors are
+    /// taken from `$color0` to `$color15` where the theme defines them, and otherwise from
+    /// [`Self::ANSI_VARIABLES`], with the bright colors the same as the normal ones.
+    fn color_scheme(context: &ThemeContext<'_>) -> Result<String, String> {
+        let color = |name: &str| Self::color(&context.variables, name);
+        let palette = (0..16)
+            .map(|index| {
+                let name = format!("color{index}");
+                if context.variables.iter().any(|v| v.0 == name) {
+                    color(&name)
+                } else {
+                    color(Self::ANSI_VARIABLES[index % 8])
+                }
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+
+        let quoted = |colors: &[String]| {
+            colors
+                .iter()
+                .map(|color| format!("\"{color}\""))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        let foreground = color("foregroundColor")?;
+        let background = color("backgroundColor")?;
+        let primary = color("primaryColor")?;
+        let split = color("borderColorInactive")?;
+
+        Ok(format!(
+            r#"# Autogenerated from {}
+[colors]
+foreground = "{foreground}"
+background = "{background}"
+cursor_bg = "{primary}"
+cursor_border = "{primary}"
+cursor_fg = "{background}"
+selection_bg = "{primary}"
+selection_fg = "{background}"
+split = "{split}"
+ansi = [{}]
+brights = [{}]
+
+[metadata]
+name = "norlyk"
+"#,
+            context.theme.get_theme_variables_css_file_path().display(),
+            quoted(&palette[..8]),
+            quoted(&palette[8..]),
+        ))
+    }
+
+    /// Gets a theme variable as a `#rrggbb` color, since `WezTerm` does not take the alpha value
+    /// of the theme's colors. Like in Sass, the last definition of the variable wins.
+    fn color(variables: &[(String, String)], name: &str) -> Result<String, String> {
+        let value = variables
+            .iter()
+            .rev()
+            .find(|v| v.0 == name)
+            .map(|v| &v.1)
+            .ok_or_else(|| format!("The theme does not define ${name}"))?;
+
+        let (red, green, blue) = HexColor::try_from(value)
+            .ok()
+            .and_then(|color| color.rgb())
+            .ok_or_else(|| format!("${name} must be a color, got: {value}"))?;
+
+        Ok(format!("#{red:02x}{green:02x}{blue:02x}"))
+    }
+
+    /// Makes `WezTerm` reload by updating the modification time of its configuration file. Nothing
+    /// is done when it is configured elsewhere, e.g. in `~/.wezterm.lua`.
+    fn reload(config_file_path: &Path) -> Result<(), String> {
+        if !config_file_path.is_file() {
+            return Ok(());
+        }
+
+        File::options()
+            .append(true)
+            .open(config_file_path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+            .map_err(|e| format!("Could not touch {}. {e}", config_file_path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn color_uses_the_last_definition() {
+        let variables = variables(&[("primaryColor", "#112233"), ("primaryColor", "#445566")]);
+
+        assert_eq!(
+            WeztermThemer::color(&variables, "primaryColor"),
+            Ok("#445566".to_string())
+        );
+    }
+
+    #[test]
+    fn color_drops_the_alpha_value() {
+        let variables = variables(&[("primaryColor", "rgba(17, 34, 51, 0.5)")]);
+
+        assert_eq!(
+            WeztermThemer::color(&variables, "primaryColor"),
+            Ok("#112233".to_string())
+        );
+    }
+
+    #[test]
+    fn color_reports_undefined_variables() {
+        assert!(WeztermThemer::color(&[], "primaryColor").is_err());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::models::settings::Settings;
+use crate::models::theme::Theme;
+use crate::services::theme_service::ThemeService;
+use crate::services::themers::hypr::HyprThemer;
+use crate::services::themers::waybar::WaybarThemer;
+use crate::utils::command_runner::CommandRunner;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::SystemTime;
+
+/// The outcome of checking a single piece of observable state after applying a theme.
+pub struct Verification {
+    pub description: String,
+    pub result: Result<(), String>,
+}
+
+/// State observed before applying a theme, which is compared against the state after applying it.
+pub struct Snapshot {
+    waybar_style_modified: Option<SystemTime>,
+}
+
+pub struct VerificationService;
+
+impl VerificationService {
+    /// Records the state that [`Self::verify`] needs in order to tell whether applying a theme
+    /// changed anything. Must be called before the theme is applied.
+    #[must_use]
+    pub fn snapshot(settings: &Settings) -> Snapshot {
+        Snapshot {
+            waybar_style_modified: WaybarThemer::style_file_path(&settings.config)
+                .ok()
+                .and_then(|path| Self::modified(&path).ok()),
+        }
+    }
+
+    /// Checks that the applications picked up the given theme, by inspecting the `current`
+    /// symlink, the generated Hyprland variables, the compiled Waybar stylesheet and the
+    /// wallpaper loaded by hyprpaper.
+    #[must_use]
+    pub fn verify(settings: &Settings, theme: &Theme, snapshot: &Snapshot) -> Vec<Verification> {
+        vec![
+            Verification {
+                description: "The current theme refers to the theme".to_string(),
+                result: Self::verify_current_theme(settings, theme),
+            },
+            Verification {
+                description: "The Hyprland style variables were generated from the theme"
+                    .to_string(),
+                result: Self::verify_hypr(settings, theme),
+            },
+            Verification {
+                description: "The Waybar stylesheet was recompiled".to_string(),
+                result: Self::verify_waybar(settings, snapshot),
+            },
+            Verification {
+                description: "hyprpaper has loaded a wallpaper from the theme".to_string(),
+                result: Self::verify_wallpaper(settings),
+            },
+        ]
+    }
+
+    fn verify_current_theme(settings: &Settings, theme: &Theme) -> Result<(), String> {
+        let current_theme_path = settings.paths.current_theme();
+
+        let target = ThemeService::current_theme_directory(settings)
+            .ok_or_else(|| format!("Could not read {}", current_theme_path.display()))?;
+
+        if target != theme.directory_path {
+            return Err(format!(
+                "{} points to {}, expected {}",
+                current_theme_path.display(),
+                target.display(),
+                theme.directory_path.display()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn verify_hypr(settings: &Settings, theme: &Theme) -> Result<(), String> {
+        let style_variables_file_path = HyprThemer::style_variables_file_path(&settings.config)?;
+
+        let contents = fs::read_to_string(&style_variables_file_path).map_err(|e| {
+            format!(
+                "Could not read {}: {e}",
+                style_variables_file_path.display()
+            )
+        })?;
+
+        let marker = format!(
+            "Autogenerated from {}",
+            theme.get_theme_variables_css_file_path().display()
+        );
+
+        if !contents.contains(&marker) {
+            return Err(format!(
+                "{} does not contain '{marker}'",
+                style_variables_file_path.display()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn verify_waybar(settings: &Settings, snapshot: &Snapshot) -> Result<(), String> {
+        let style_file_path = WaybarThemer::style_file_path(&settings.config)?;
+        let modified = Self::modified(&style_file_path)?;
+
+        if let Some(previously_modified) = snapshot.waybar_style_modified
+            && modified <= previously_modified
+        {
+            return Err(format!("{} was not modified", style_file_path.display()));
+        }
+
+        Ok(())
+    }
+
+    fn verify_wallpaper(settings: &Settings) -> Result<(), String> {
+        let wallpaper_dir_path = settings.paths.current_wallpapers();
+
+        let output = CommandRunner::run(Command::new("hyprctl").arg("hyprpaper").arg("listactive"))
+            .map_err(|e| format!("Could not list active wallpapers: {e}"))?;
+
+        let response = String::from_utf8_lossy(&output.stdout);
+
+        if !response.contains(&wallpaper_dir_path.display().to_string()) {
+            return Err(format!(
+                "No active wallpaper from {}: {}",
+                wallpaper_dir_path.display(),
+                response.trim()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn modified(path: &Path) -> Result<SystemTime, String> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| format!("Could not get modification time of {}: {e}", path.display()))
+    }
+}
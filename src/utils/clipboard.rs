@@ -0,0 +1,53 @@
+use crate::utils::command_runner::CommandRunner;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub struct Clipboard;
+
+impl Clipboard {
+    /// The programs tried for copying, in order, with their arguments for reading the text from
+    /// stdin into the clipboard.
+    const PROGRAMS: [(&'static str, &'static [&'static str]); 2] =
+        [("wl-copy", &[]), ("xclip", &["-selection", "clipboard"])];
+
+    /// Copies the text to the system clipboard, using `wl-copy` on Wayland, or `xclip` if
+    /// `wl-copy` is not installed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither program is installed, or if the program fails.
+    pub fn copy(text: &str) -> Result<(), String> {
+        let Some((program, args)) = Self::PROGRAMS
+            .iter()
+            .find(|(program, _)| CommandRunner::find_executable(program).is_some())
+        else {
+            return Err("Install wl-copy or xclip to copy to the clipboard".to_string());
+        };
+
+        let mut child = CommandRunner::spawn(
+            Command::new(program)
+                .args(*args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null()),
+        )
+        .map_err(|e| format!("Could not execute {program}: {e}"))?;
+
+        // Dropping stdin closes it, so that the program knows the text is complete
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .map_err(|e| format!("Could not write to {program}: {e}"))?;
+        }
+
+        let status = child
+            .wait()
+            .map_err(|e| format!("Could not wait for {program}: {e}"))?;
+
+        if !status.success() {
+            return Err(format!("{program} failed with {status}"));
+        }
+
+        Ok(())
+    }
+}
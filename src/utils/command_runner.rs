@@ -0,0 +1,348 @@
+use serde::Serialize;
+use std::env;
+use std::io::{self, Read};
+use std::iter;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The commands executed since [`CommandRunner::start_recording()`], or `None` when not recording.
+static RECORDED_COMMANDS: Mutex<Option<Vec<ExecutedCommand>>> = Mutex::new(None);
+
+/// An external command executed by [`CommandRunner`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutedCommand {
+    pub command: String,
+    /// The exit code, or `None` if the command could not be started, was killed by a signal, or
+    /// was spawned without waiting for it to exit.
+    pub exit_code: Option<i32>,
+}
+
+/// Executes external commands, optionally recording them for the apply log.
+pub struct CommandRunner;
+
+impl CommandRunner {
+    /// The environment variables a detached program keeps, which are the ones describing the
+    /// graphical session it runs in.
+    const SESSION_ENV: &'static [&'static str] = &[
+        "HOME",
+        "PATH",
+        "USER",
+        "LANG",
+        "LC_ALL",
+        "XDG_RUNTIME_DIR",
+        "XDG_CONFIG_HOME",
+        "XDG_DATA_HOME",
+        "XDG_DATA_DIRS",
+        "XDG_CURRENT_DESKTOP",
+        "XDG_SESSION_TYPE",
+        "WAYLAND_DISPLAY",
+        "DISPLAY",
+        "DBUS_SESSION_BUS_ADDRESS",
+        "HYPRLAND_INSTANCE_SIGNATURE",
+    ];
+
+    /// Runs the command to completion, capturing its output.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be executed.
+    pub fn output(command: &mut Command) -> io::Result<Output> {
+        let output = command.output();
+
+        Self::record(
+            command,
+            output.as_ref().ok().and_then(|output| output.status.code()),
+        );
+
+        output
+    }
+
+    /// Runs the command to completion like [`Self::output()`], but kills it if it has not exited
+    /// within `timeout`. The output is read while waiting, so that a command writing more than
+    /// fits in a pipe does not block until it is killed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be executed, and an error of kind
+    /// [`io::ErrorKind::TimedOut`] if it was killed.
+    pub fn output_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+        let output = Self::wait_with_timeout(command, timeout);
+
+        Self::record(
+            command,
+            output.as_ref().ok().and_then(|output| output.status.code()),
+        );
+
+        output
+    }
+
+    /// Runs the command to completion like [`Self::output()`], but also treats a non-zero exit code
+    /// as an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be executed, or if it exits unsuccessfully, in which
+    /// case the error contains what the command wrote to stderr.
+    pub fn run(command: &mut Command) -> Result<Output, String> {
+        let output = Self::output(command)
+            .map_err(|e| format!("Could not execute '{}': {e}", Self::describe(command)))?;
+
+        if !output.status.success() {
+            return Err(Self::describe_failure(command, &output));
+        }
+
+        Ok(output)
+    }
+
+    /// Describes an unsuccessful run of the command, including what it wrote to stderr.
+    #[must_use]
+    pub fn describe_failure(command: &Command, output: &Output) -> String {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        format!(
+            "'{}' failed with {}: {}",
+            Self::describe(command),
+            output.status,
+            stderr.trim()
+        )
+    }
+
+    /// Builds a command from a command line such as `code --wait`, splitting it into the program
+    /// and its arguments the way a shell would, so that quoted arguments can contain spaces.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command line is empty or its quotes are not closed.
+    pub fn parse(command_line: &str) -> Result<Command, String> {
+        let parts = shlex::split(command_line)
+            .ok_or_else(|| format!("Invalid command '{command_line}': unclosed quote"))?;
+
+        let Some((program, args)) = parts.split_first() else {
+            return Err(format!("Invalid command '{command_line}': it is empty"));
+        };
+
+        let mut command = Command::new(program);
+        command.args(args);
+        Ok(command)
+    }
+
+    /// Starts the command without waiting for it to exit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be executed.
+    pub fn spawn(command: &mut Command) -> io::Result<Child> {
+        let child = command.spawn();
+        Self::record(command, None);
+        child
+    }
+
+    /// Starts the program in a session of its own with `setsid --fork`, so that it has no
+    /// controlling terminal and keeps running after the theme picker and the terminal it was
+    /// started from exit. Its standard streams are null, and its environment only has the
+    /// [`Self::SESSION_ENV`] variables, so that nothing specific to the terminal is inherited.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `setsid` cannot be executed.
+    pub fn spawn_detached(program: &str) -> io::Result<Child> {
+        let mut command = Command::new("setsid");
+        command
+            .arg("--fork")
+            .arg(program)
+            .env_clear()
+            .envs(
+                Self::SESSION_ENV
+                    .iter()
+                    .filter_map(|name| Some((name, env::var_os(name)?))),
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+
+        Self::spawn(&mut command)
+    }
+
+    /// Finds the program in one of the directories in `PATH`, returning its full path.
+    #[must_use]
+    pub fn find_executable(program: &str) -> Option<PathBuf> {
+        let path = env::var_os("PATH")?;
+
+        env::split_paths(&path)
+            .map(|directory| directory.join(program))
+            .find(|candidate| {
+                candidate.metadata().is_ok_and(|metadata| {
+                    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+                })
+            })
+    }
+
+    /// Starts recording every command executed through [`CommandRunner`], discarding any commands
+    /// recorded previously.
+    pub fn start_recording() {
+        if let Ok(mut recorded_commands) = RECORDED_COMMANDS.lock() {
+            *recorded_commands = Some(Vec::new());
+        }
+    }
+
+    /// Stops recording, returning the commands executed since [`Self::start_recording()`].
+    #[must_use]
+    pub fn stop_recording() -> Vec<ExecutedCommand> {
+        RECORDED_COMMANDS
+            .lock()
+            .ok()
+            .and_then(|mut recorded_commands| recorded_commands.take())
+            .unwrap_or_default()
+    }
+
+    fn wait_with_timeout(command: &mut Command, timeout: Duration) -> io::Result<Output> {
+        // A timeout too long to represent is never reached
+        let deadline = Instant::now().checked_add(timeout);
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdout = Self::read_in_background(child.stdout.take());
+        let stderr = Self::read_in_background(child.stderr.take());
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                child.kill()?;
+                child.wait()?;
+
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "'{}' did not exit within {} seconds",
+                        Self::describe(command),
+                        timeout.as_secs()
+                    ),
+                ));
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        Ok(Output {
+            status,
+            stdout: Self::join_reader(stdout)?,
+            stderr: Self::join_reader(stderr)?,
+        })
+    }
+
+    /// Reads the pipe to its end on a thread of its own.
+    fn read_in_background(
+        pipe: Option<impl Read + Send + 'static>,
+    ) -> JoinHandle<io::Result<Vec<u8>>> {
+        thread::spawn(move || {
+            let mut buffer = Vec::new();
+
+            if let Some(mut pipe) = pipe {
+                pipe.read_to_end(&mut buffer)?;
+            }
+
+            Ok(buffer)
+        })
+    }
+
+    fn join_reader(reader: JoinHandle<io::Result<Vec<u8>>>) -> io::Result<Vec<u8>> {
+        reader
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("Reading the output of a command panicked")))
+    }
+
+    fn record(command: &Command, exit_code: Option<i32>) {
+        let Ok(mut recorded_commands) = RECORDED_COMMANDS.lock() else {
+            return;
+        };
+
+        let Some(recorded_commands) = recorded_commands.as_mut() else {
+            return;
+        };
+
+        recorded_commands.push(ExecutedCommand {
+            command: Self::describe(command),
+            exit_code,
+        });
+    }
+
+    /// Formats the program and arguments of the command, separated by spaces.
+    #[must_use]
+    pub fn describe(command: &Command) -> String {
+        iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_with_timeout_reads_more_than_fits_in_a_pipe() {
+        let output = CommandRunner::output_with_timeout(
+            Command::new("head")
+                .arg("-c")
+                .arg("1000000")
+                .arg("/dev/zero"),
+            Duration::from_secs(10),
+        )
+        .unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout.len(), 1_000_000);
+    }
+
+    #[test]
+    fn output_with_timeout_kills_commands_that_hang() {
+        let error = CommandRunner::output_with_timeout(
+            Command::new("sleep").arg("10"),
+            Duration::from_millis(100),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn output_with_timeout_allows_timeouts_too_long_to_represent() {
+        let output =
+            CommandRunner::output_with_timeout(&mut Command::new("true"), Duration::MAX).unwrap();
+
+        assert!(output.status.success());
+    }
+
+    #[test]
+    fn parse_splits_like_a_shell() {
+        let command = CommandRunner::parse(r#"code --wait "My Editor" it\'s"#).unwrap();
+
+        assert_eq!(command.get_program(), "code");
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            ["--wait", "My Editor", "it's"]
+        );
+    }
+
+    #[test]
+    fn parse_fails_for_empty_command_lines() {
+        assert!(CommandRunner::parse("  ").is_err());
+    }
+
+    #[test]
+    fn parse_fails_for_unclosed_quotes() {
+        assert!(CommandRunner::parse("code \"--wait").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Directory;
+
+impl Directory {
+    /// Recursively copies the contents of `source` into `destination`, creating it if needed.
+    /// Symbolic links are followed, so the copy does not depend on the source.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a directory cannot be read or created, a file cannot be copied, or a
+    /// symbolic link leads back to a directory being copied, which would be copied endlessly.
+    pub fn copy(source: &Path, destination: &Path) -> Result<(), String> {
+        Self::copy_within(source, destination, &mut Vec::new())
+    }
+
+    /// Copies `source` like [`Self::copy()`], where `ancestors` are the canonical paths of the
+    /// directories that `source` is being copied as a part of.
+    fn copy_within(
+        source: &Path,
+        destination: &Path,
+        ancestors: &mut Vec<PathBuf>,
+    ) -> Result<(), String> {
+        let canonical_source = source
+            .canonicalize()
+            .map_err(|e| format!("Failed to resolve {}: {e}", source.display()))?;
+
+        if ancestors.contains(&canonical_source) {
+            return Err(format!(
+                "Failed to copy {}: it links to {}, which contains it",
+                source.display(),
+                canonical_source.display()
+            ));
+        }
+
+        fs::create_dir_all(destination)
+            .map_err(|e| format!("Failed to create {}: {e}", destination.display()))?;
+
+        let entries = fs::read_dir(source)
+            .map_err(|e| format!("Failed to read {}: {e}", source.display()))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read {}: {e}", source.display()))?;
+            let source_path = entry.path();
+            let destination_path = destination.join(entry.file_name());
+
+            if source_path.is_dir() {
+                ancestors.push(canonical_source.clone());
+                let copied = Self::copy_within(&source_path, &destination_path, ancestors);
+                ancestors.pop();
+                copied?;
+            } else {
+                fs::copy(&source_path, &destination_path).map_err(|e| {
+                    format!(
+                        "Failed to copy {} to {}: {e}",
+                        source_path.display(),
+                        destination_path.display()
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recursively lists the files in `path`, sorted by path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a directory cannot be read.
+    pub fn files(path: &Path) -> Result<Vec<PathBuf>, String> {
+        let entries =
+            fs::read_dir(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+        let mut files = Vec::new();
+
+        for entry in entries {
+            let entry_path = entry
+                .map_err(|e| format!("Failed to read {}: {e}", path.display()))?
+                .path();
+
+            if entry_path.is_dir() {
+                files.extend(Self::files(&entry_path)?);
+            } else {
+                files.push(entry_path);
+            }
+        }
+
+        files.sort();
+
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix;
+    use tempfile::TempDir;
+
+    #[test]
+    fn copy_follows_symlinks() {
+        let directory = TempDir::new().unwrap();
+        let source = directory.path().join("source");
+        let destination = directory.path().join("destination");
+        fs::create_dir(&source).unwrap();
+        fs::write(directory.path().join("palette.scss"), "$bg: #000;").unwrap();
+        unix::fs::symlink(
+            directory.path().join("palette.scss"),
+            source.join("palette.scss"),
+        )
+        .unwrap();
+
+        Directory::copy(&source, &destination).unwrap();
+
+        let copied = destination.join("palette.scss");
+        assert!(!copied.is_symlink());
+        assert_eq!(fs::read_to_string(copied).unwrap(), "$bg: #000;");
+    }
+
+    #[test]
+    fn copy_fails_for_symlinks_to_a_directory_being_copied() {
+        let directory = TempDir::new().unwrap();
+        let source = directory.path().join("source");
+        fs::create_dir_all(source.join("wallpapers")).unwrap();
+        unix::fs::symlink(&source, source.join("wallpapers/loop")).unwrap();
+
+        let error = Directory::copy(&source, &directory.path().join("destination")).unwrap_err();
+
+        assert!(error.contains("which contains it"), "{error}");
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+use std::cell::RefCell;
+#[cfg(test)]
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(test)]
+use std::path::PathBuf;
+
+/// The file operations used to generate configuration files, so that the generated contents can be
+/// inspected without touching the disk.
+pub trait FileSystem {
+    /// Reads the entire contents of a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file does not exist or cannot be read.
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    /// Writes the contents to a file, replacing it if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()>;
+
+    /// Creates a directory and all of its missing parents.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a directory cannot be created.
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Called after an external command, such as `sass`, wrote the file.
+    fn written_externally(&self, _path: &Path) {}
+}
+
+/// The real file system, using [`std::fs`].
+pub struct SystemFs;
+
+impl FileSystem for SystemFs {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+}
+
+/// The real file system, which also prints the path and contents of every written file to stderr,
+/// for debugging templates.
+pub struct VerboseFs;
+
+impl VerboseFs {
+    fn print(path: &Path, contents: &str) {
+        eprintln!("==> {} <==", path.display());
+        eprintln!("{}", contents.trim_end());
+    }
+}
+
+impl FileSystem for VerboseFs {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        SystemFs.read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        SystemFs.write(path, contents)?;
+        Self::print(path, contents);
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        SystemFs.create_dir_all(path)
+    }
+
+    fn written_externally(&self, path: &Path) {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::print(path, &contents),
+            Err(e) => eprintln!("Could not read {}: {e}", path.display()),
+        }
+    }
+}
+
+/// A file system kept in memory, where directories always exist, for testing the themers without
+/// touching the disk.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryFs {
+    files: RefCell<HashMap<PathBuf, String>>,
+}
+
+#[cfg(test)]
+impl MemoryFs {
+    /// Adds a file with the given contents.
+    #[must_use]
+    pub fn with_file(self, path: impl Into<PathBuf>, contents: &str) -> Self {
+        self.files
+            .borrow_mut()
+            .insert(path.into(), contents.to_string());
+
+        self
+    }
+
+    /// Gets the contents of a file, or `None` if it has not been written.
+    #[must_use]
+    pub fn contents(&self, path: &Path) -> Option<String> {
+        self.files.borrow().get(path).cloned()
+    }
+}
+
+#[cfg(test)]
+impl FileSystem for MemoryFs {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.contents(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} does not exist", path.display()),
+            )
+        })
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> io::Result<()> {
+        self.files
+            .borrow_mut()
+            .insert(path.to_path_buf(), contents.to_string());
+
+        Ok(())
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
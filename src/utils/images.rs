@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Images;
+
+impl Images {
+    /// The file extensions recognized as images, in lowercase.
+    pub const EXTENSIONS: &'static [&'static str] = &["png", "jpg", "jpeg", "bmp"];
+
+    /// The file extensions recognized as videos, in lowercase.
+    pub const VIDEO_EXTENSIONS: &'static [&'static str] = &["mp4", "webm", "mkv"];
+
+    /// Checks whether the path has one of the [`Self::EXTENSIONS`], ignoring case.
+    #[must_use]
+    pub fn is_image(path: &Path) -> bool {
+        Self::has_extension(path, Self::EXTENSIONS)
+    }
+
+    /// Checks whether the path has one of the [`Self::VIDEO_EXTENSIONS`], ignoring case.
+    #[must_use]
+    pub fn is_video(path: &Path) -> bool {
+        Self::has_extension(path, Self::VIDEO_EXTENSIONS)
+    }
+
+    fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+        path.extension().is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|known_extension| extension.eq_ignore_ascii_case(known_extension))
+        })
+    }
+
+    /// Lists the image files directly inside the directory, sorted by path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read.
+    pub fn list(directory: &Path) -> Result<Vec<PathBuf>, String> {
+        Self::list_matching(directory, Self::is_image)
+    }
+
+    /// Lists the video files directly inside the directory, sorted by path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read.
+    pub fn list_videos(directory: &Path) -> Result<Vec<PathBuf>, String> {
+        Self::list_matching(directory, Self::is_video)
+    }
+
+    /// Encodes a single pixel of the color as a 24-bit BMP image, which hyprpaper stretches over
+    /// the whole monitor.
+    #[must_use]
+    pub fn solid_color_bmp((red, green, blue): (u8, u8, u8)) -> Vec<u8> {
+        const HEADER_SIZE: u32 = 14;
+        const INFO_HEADER_SIZE: u32 = 40;
+        // Rows are padded to a multiple of four bytes
+        const ROW_SIZE: u32 = 4;
+
+        let mut bmp = Vec::new();
+
+        bmp.extend_from_slice(b"BM");
+        bmp.extend_from_slice(&(HEADER_SIZE + INFO_HEADER_SIZE + ROW_SIZE).to_le_bytes());
+        bmp.extend_from_slice(&[0; 4]);
+        bmp.extend_from_slice(&(HEADER_SIZE + INFO_HEADER_SIZE).to_le_bytes());
+
+        bmp.extend_from_slice(&INFO_HEADER_SIZE.to_le_bytes());
+        bmp.extend_from_slice(&1i32.to_le_bytes()); // Width
+        bmp.extend_from_slice(&1i32.to_le_bytes()); // Height
+        bmp.extend_from_slice(&1u16.to_le_bytes()); // Color planes
+        bmp.extend_from_slice(&24u16.to_le_bytes()); // Bits per pixel
+        bmp.extend_from_slice(&0u32.to_le_bytes()); // No compression
+        bmp.extend_from_slice(&ROW_SIZE.to_le_bytes());
+        bmp.extend_from_slice(&[0; 16]); // Resolution and palette, unused
+
+        bmp.extend_from_slice(&[blue, green, red, 0]);
+
+        bmp
+    }
+
+    fn list_matching(directory: &Path, matches: fn(&Path) -> bool) -> Result<Vec<PathBuf>, String> {
+        let entries = fs::read_dir(directory)
+            .map_err(|e| format!("Failed to read {}: {e}", directory.display()))?;
+
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_file() && matches(path))
+            .collect();
+
+        files.sort();
+
+        Ok(files)
+    }
+}
@@ -1,2 +1,9 @@
+pub mod clipboard;
+pub mod command_runner;
+pub mod directory;
+pub mod file_system;
+pub mod images;
 pub mod paths;
 pub mod symlink;
+pub mod template;
+pub mod terminal_graphics;
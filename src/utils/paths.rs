@@ -1,7 +1,20 @@
+use regex::Regex;
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
 
-pub struct Paths;
+/// Matches an environment variable written as `$NAME` or `${NAME}`, capturing the name.
+static VARIABLE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\$(?:\{([A-Za-z_][A-Za-z0-9_]*)\}|([A-Za-z_][A-Za-z0-9_]*))").unwrap()
+});
+
+/// The directories the theme picker reads the themes from and keeps its state in, resolved once at
+/// startup, see [`crate::services::config_service::ConfigService::settings()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    config_path: PathBuf,
+    state_path: PathBuf,
+}
 
 impl Paths {
     /// Gets the path to the user's home directory.
@@ -18,27 +31,151 @@ impl Paths {
         Ok(PathBuf::from(home))
     }
 
-    /// Gets the path to the directory containing the theme picker's configuration files, located at
-    /// `~/.local/share/norlyk-themes/`.
+    /// Creates the paths of a theme directory and a state directory.
+    #[must_use]
+    pub fn new(config_path: PathBuf, state_path: PathBuf) -> Self {
+        Paths {
+            config_path,
+            state_path,
+        }
+    }
+
+    /// Gets the default path to the directory containing the theme picker's configuration files,
+    /// located at `~/.local/share/norlyk-themes/`.
     ///
     /// # Errors
     ///
     /// Returns an error if the environment variable `HOME` is not set.
     ///
-    pub fn config_path() -> Result<PathBuf, String> {
+    pub fn default_config_path() -> Result<PathBuf, String> {
         let home_path = Self::user_home()?;
         Ok(home_path.join(".local/share/norlyk-themes"))
     }
 
-    /// Gets the path to the directory containing the currently selected theme.
-    /// `~/.local/share/norlyk-themes/current/`.
+    /// Gets the path to the directory containing the theme picker's configuration files, which is
+    /// [`Self::default_config_path()`] unless another directory is given, e.g. on the command line.
+    #[must_use]
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
+    }
+
+    /// Gets the path to the directory containing the theme picker's state, such as the `current`
+    /// symlink and the files recording the last selection. This is [`Self::config_path()`] unless
+    /// another directory is configured, e.g. to keep the themes on a read-only mount.
+    #[must_use]
+    pub fn state_path(&self) -> &Path {
+        &self.state_path
+    }
+
+    /// Gets the path to the directory containing the currently selected theme, located at
+    /// `current/` in [`Self::state_path()`].
+    #[must_use]
+    pub fn current_theme(&self) -> PathBuf {
+        self.state_path.join("current")
+    }
+
+    /// Gets the path to the directory containing the wallpapers of the currently selected theme,
+    /// located at `current/wallpapers/` in [`Self::state_path()`].
+    #[must_use]
+    pub fn current_wallpapers(&self) -> PathBuf {
+        self.current_theme().join("wallpapers")
+    }
+
+    /// Gets the path to the file recording the default theme, located at `default-theme` in
+    /// [`Self::state_path()`].
+    #[must_use]
+    pub fn default_theme_file(&self) -> PathBuf {
+        self.state_path.join("default-theme")
+    }
+
+    /// Gets the path to the file recording the index of the last wallpaper the sequential wallpaper
+    /// strategy selected for a theme, named after the theme's directory in `wallpaper-indexes/` in
+    /// [`Self::state_path()`], so that every theme starts at its first wallpaper.
+    #[must_use]
+    pub fn wallpaper_index_file(&self, theme_dir_name: &str) -> PathBuf {
+        self.state_path
+            .join("wallpaper-indexes")
+            .join(theme_dir_name)
+    }
+
+    /// Gets the path to the file recording the wallpaper last set on each monitor, located at
+    /// `wallpaper-assignments` in [`Self::state_path()`].
+    #[must_use]
+    pub fn wallpaper_assignments_file(&self) -> PathBuf {
+        self.state_path.join("wallpaper-assignments")
+    }
+
+    /// Gets the path to the file recording the theme last selected in the theme picker, located at
+    /// `last-selection` in [`Self::state_path()`].
+    #[must_use]
+    pub fn last_selection_file(&self) -> PathBuf {
+        self.state_path.join("last-selection")
+    }
+
+    /// Gets the path to the directory containing the generated images of themes with a solid color
+    /// as their wallpaper, located at `solid-color-wallpapers/` in [`Self::state_path()`].
+    #[must_use]
+    pub fn solid_color_wallpapers(&self) -> PathBuf {
+        self.state_path.join("solid-color-wallpapers")
+    }
+
+    /// Gets the default path to the global configuration file, located at
+    /// `$XDG_CONFIG_HOME/norlyk/config.toml`, or `~/.config/norlyk/config.toml` if
+    /// `XDG_CONFIG_HOME` is not set.
     ///
     /// # Errors
     ///
-    /// Returns an error if the environment variable `HOME` is not set.
+    /// Returns an error if neither `XDG_CONFIG_HOME` nor `HOME` is set.
+    ///
+    pub fn global_config_file() -> Result<PathBuf, String> {
+        let config_home_path = match env::var("XDG_CONFIG_HOME") {
+            Ok(path) if !path.is_empty() => PathBuf::from(path),
+            _ => Self::user_home()?.join(".config"),
+        };
+
+        Ok(config_home_path.join("norlyk/config.toml"))
+    }
+
+    /// Expands a leading `~` to the user's home directory, and environment variables written as
+    /// `$NAME` or `${NAME}` to their values.
     ///
-    pub fn current_theme() -> Result<PathBuf, String> {
-        let config_path = Self::config_path()?;
-        Ok(config_path.join("current"))
+    /// # Errors
+    ///
+    /// Returns an error if the environment variable `HOME` is not set while the path starts with
+    /// `~`, or if a referenced environment variable is not set.
+    ///
+    pub fn expand(path: &str) -> Result<PathBuf, String> {
+        let mut expanded = String::new();
+        let mut last_end = 0;
+
+        for captures in VARIABLE_REGEX.captures_iter(path) {
+            let (Some(whole), Some(name)) = (captures.get(0), captures.get(1).or(captures.get(2)))
+            else {
+                continue;
+            };
+
+            let value = env::var(name.as_str()).map_err(|_| {
+                format!(
+                    "Could not expand {path}: the environment variable {} is not set",
+                    name.as_str()
+                )
+            })?;
+
+            expanded.push_str(&path[last_end..whole.start()]);
+            expanded.push_str(&value);
+            last_end = whole.end();
+        }
+
+        expanded.push_str(&path[last_end..]);
+
+        if expanded == "~" {
+            return Self::user_home();
+        }
+
+        if let Some(relative_path) = expanded.strip_prefix("~/") {
+            return Ok(Self::user_home()?.join(relative_path));
+        }
+
+        Ok(PathBuf::from(expanded))
     }
 }
@@ -1,7 +1,8 @@
 use std::fs;
 use std::io::ErrorKind;
+use std::os::unix;
 use std::path::Path;
-use std::process::Command;
+use std::process;
 
 pub struct Symlink;
 
@@ -24,33 +25,133 @@ impl Symlink {
         }
     }
 
+    /// Removes the symbolic link or file if it exists. A symbolic link is removed without touching
+    /// its target. A real directory is never removed, since it may be the user's own, e.g. a
+    /// Neovim config that the theme picker would otherwise replace with a link.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if checking the path fails, the path is a real directory, or removing it
+    /// fails.
+    pub fn remove(path: &Path) -> Result<(), String> {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => {
+                return Err(format!(
+                    "Could not check existence of {}, {e}",
+                    path.display()
+                ));
+            }
+        };
+
+        if metadata.is_dir() {
+            return Err(format!(
+                "{} is a directory, remove it to replace it with a symlink",
+                path.display()
+            ));
+        }
+
+        fs::remove_file(path).map_err(|e| format!("Failed to remove {}: {e}", path.display()))
+    }
+
     /// Creates a symbolic link.
     ///
-    /// If `destination` already exists, it is removed first.
+    /// If `destination` is a file or symbolic link, it is replaced. The link is created under a
+    /// temporary name and renamed over `destination`, which is atomic, so that `destination`
+    /// exists throughout even if the process is killed. A real directory at `destination` is not
+    /// replaced, see [`Self::remove()`].
     ///
     /// # Errors
     ///
-    /// Returns an error if checking the destination fails, removing an existing
-    /// destination fails, or creating the symbolic link fails.
+    /// Returns an error if `destination` is a real directory, checking it fails, or creating the
+    /// symbolic link fails.
     pub fn create(source: &Path, destination: &Path) -> Result<(), String> {
-        if Self::exists(destination)? {
-            fs::remove_file(destination)
-                .map_err(|e| format!("Failed to remove {}: {e}", destination.display()))?;
+        if fs::symlink_metadata(destination).is_ok_and(|metadata| metadata.is_dir()) {
+            return Err(format!(
+                "{} is a directory, remove it to replace it with a symlink to {}",
+                destination.display(),
+                source.display()
+            ));
         }
 
-        Command::new("ln")
-            .arg("-s")
-            .arg(source)
-            .arg(destination)
-            .output()
-            .map_err(|e| {
-                format!(
-                    "Failed to create symlink from {} to {}: {e}",
-                    source.display(),
-                    destination.display()
-                )
-            })?;
-
-        Ok(())
+        let file_name = destination
+            .file_name()
+            .ok_or_else(|| format!("Invalid symlink path {}", destination.display()))?;
+        let temporary_path = destination.with_file_name(format!(
+            ".{}.{}.tmp",
+            file_name.to_string_lossy(),
+            process::id()
+        ));
+
+        // Left over if an earlier run was killed between creating and renaming it
+        Self::remove(&temporary_path)?;
+
+        unix::fs::symlink(source, &temporary_path).map_err(|e| {
+            format!(
+                "Failed to create symlink from {} to {}: {e}",
+                source.display(),
+                destination.display()
+            )
+        })?;
+
+        fs::rename(&temporary_path, destination).map_err(|e| {
+            let _ = fs::remove_file(&temporary_path);
+            format!(
+                "Failed to replace {} with a symlink to {}: {e}",
+                destination.display(),
+                source.display()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn create_replaces_an_existing_symlink() {
+        let directory = TempDir::new().unwrap();
+        let link = directory.path().join("current");
+        unix::fs::symlink("/old", &link).unwrap();
+
+        Symlink::create(Path::new("/new"), &link).unwrap();
+
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("/new"));
+    }
+
+    #[test]
+    fn create_leaves_a_real_directory_alone() {
+        let directory = TempDir::new().unwrap();
+        let destination = directory.path().join("nvim");
+        fs::create_dir(&destination).unwrap();
+        fs::write(destination.join("init.lua"), "-- mine").unwrap();
+
+        assert!(Symlink::create(Path::new("/new"), &destination).is_err());
+        assert!(destination.join("init.lua").is_file());
+    }
+
+    #[test]
+    fn remove_leaves_a_real_directory_alone() {
+        let directory = TempDir::new().unwrap();
+
+        assert!(Symlink::remove(directory.path()).is_err());
+        assert!(directory.path().is_dir());
+    }
+
+    #[test]
+    fn remove_keeps_the_target_of_a_symlink() {
+        let directory = TempDir::new().unwrap();
+        let target = directory.path().join("target");
+        let link = directory.path().join("link");
+        fs::create_dir(&target).unwrap();
+        unix::fs::symlink(&target, &link).unwrap();
+
+        Symlink::remove(&link).unwrap();
+
+        assert!(!Symlink::exists(&link).unwrap());
+        assert!(target.is_dir());
     }
 }
@@ -0,0 +1,94 @@
+use crate::models::hex_color::HexColor;
+use regex::Regex;
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// Matches a `__variableName__` placeholder, compiled once and shared by every template.
+static PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"__(:?.*)__").unwrap());
+
+/// A configuration file template, where every `__variableName__` placeholder is replaced by the
+/// value of the matching theme variable.
+pub struct Template;
+
+impl Template {
+    /// Name of the theme variable holding the terminal's background opacity, from 0.0 to 1.0.
+    const OPACITY_VARIABLE: &'static str = "terminal_opacity";
+
+    /// Replaces the placeholders of the template with the values of the variables. Colors are
+    /// converted to hex, anything else (font sizes, ...) is used as is. Lines with a placeholder
+    /// that none of the variables resolve are left untouched.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `$terminal_opacity` is not a number between 0.0 and 1.0, naming the
+    /// `destination` the template is rendered for.
+    pub fn render(
+        template: &str,
+        variables: &[(String, String)],
+        destination: &Path,
+    ) -> Result<String, String> {
+        let mut output = String::new();
+
+        for line in template.lines() {
+            let Some(captures) = PLACEHOLDER_REGEX.captures(line) else {
+                writeln!(output, "{line}").map_err(|e| format!("Could not write line. {e}"))?;
+                continue;
+            };
+
+            if captures.len() != 2 {
+                writeln!(output, "{line}").map_err(|e| format!("Could not write line. {e}"))?;
+                continue;
+            }
+
+            let replacement_variable = captures[0].to_string();
+            let variable_name = captures[1].to_string();
+
+            let Some(variable) = variables.iter().find(|v| v.0 == variable_name) else {
+                writeln!(output, "{line}").map_err(|e| format!("Could not write line. {e}"))?;
+                continue;
+            };
+
+            let variable_value = &variable.1;
+            let replacement: String = if variable_name == Self::OPACITY_VARIABLE {
+                Self::validate_opacity(variable_value).map_err(|e| {
+                    format!(
+                        "{e} (while resolving ${variable_name} in {})",
+                        destination.display()
+                    )
+                })?
+            } else {
+                match HexColor::try_from(variable_value) {
+                    Ok(hex_color) => hex_color.into(),
+                    Err(_) => variable_value.clone(),
+                }
+            };
+            let new_line = line.replace(&replacement_variable, &replacement);
+
+            writeln!(output, "{new_line}").map_err(|e| format!("Could not write line. {e}"))?;
+        }
+
+        Ok(output)
+    }
+
+    /// Finds the placeholders of the template that none of the variables resolve.
+    #[must_use]
+    pub fn unresolved_placeholders(template: &str, variables: &[(String, String)]) -> Vec<String> {
+        template
+            .lines()
+            .filter_map(|line| PLACEHOLDER_REGEX.captures(line))
+            .map(|captures| captures[1].to_string())
+            .filter(|variable_name| !variables.iter().any(|v| &v.0 == variable_name))
+            .collect()
+    }
+
+    fn validate_opacity(value: &str) -> Result<String, String> {
+        match value.parse::<f64>() {
+            Ok(opacity) if (0.0..=1.0).contains(&opacity) => Ok(value.to_string()),
+            _ => Err(format!(
+                "${} must be a number between 0.0 and 1.0, got: {value}",
+                Self::OPACITY_VARIABLE
+            )),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use base64::prelude::{BASE64_STANDARD, Engine};
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Draws images using the kitty graphics protocol.
+pub struct TerminalGraphics;
+
+impl TerminalGraphics {
+    /// Checks whether the terminal supports the kitty graphics protocol, based on the environment
+    /// variables set by the terminals known to support it.
+    #[must_use]
+    pub fn is_supported() -> bool {
+        env::var_os("KITTY_WINDOW_ID").is_some()
+            || env::var("TERM").is_ok_and(|term| term == "xterm-kitty" || term == "xterm-ghostty")
+            || env::var("TERM_PROGRAM")
+                .is_ok_and(|program| program == "WezTerm" || program == "ghostty")
+    }
+
+    /// Checks whether the file is a PNG file, by its extension. The kitty graphics protocol is the
+    /// only one supported, and it cannot draw any other format from a file.
+    #[must_use]
+    pub fn is_png(path: &Path) -> bool {
+        path.extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+    }
+
+    /// Draws the PNG file at `path` at the cursor position, scaled to fill the given number of
+    /// columns and rows. The terminal reads the file itself, so it is never decoded here.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the escape sequence fails.
+    pub fn draw_png(
+        writer: &mut impl Write,
+        path: &Path,
+        columns: u16,
+        rows: u16,
+    ) -> io::Result<()> {
+        let encoded_path = BASE64_STANDARD.encode(path.as_os_str().as_encoded_bytes());
+
+        // a=T: transmit and display, f=100: PNG, t=f: read from file, q=2: suppress responses,
+        // C=1: do not move the cursor
+        write!(
+            writer,
+            "\x1b_Ga=T,f=100,t=f,q=2,C=1,c={columns},r={rows};{encoded_path}\x1b\\"
+        )?;
+
+        writer.flush()
+    }
+
+    /// Deletes every image drawn on the screen.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing the escape sequence fails.
+    pub fn clear(writer: &mut impl Write) -> io::Result<()> {
+        write!(writer, "\x1b_Ga=d,q=2\x1b\\")?;
+        writer.flush()
+    }
+}